@@ -0,0 +1,402 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use rand_core::{CryptoRng, OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto_utils::rng::random_uuid;
+
+/// Version byte prefixed to every encoded [`ContentMessage`], bumped at every layout change
+/// so that older encodings are rejected instead of misread.
+///
+/// - `1`: Bare [`Content`], with timestamps inside the variants.
+/// - `2`: Message ID and timestamp in a [`ContentMessage`] header.
+/// - `3`: [`Content::EndSession`].
+/// - `4`: Expiry timer in the header, and [`Content::ExpirationTimerUpdate`].
+const CONTENT_VERSION: u8 = 4;
+
+const TAG_TEXT: u8 = 0;
+const TAG_REACTION: u8 = 1;
+const TAG_RECEIPT: u8 = 2;
+const TAG_TYPING: u8 = 3;
+const TAG_EDIT: u8 = 4;
+const TAG_DELETE: u8 = 5;
+const TAG_END_SESSION: u8 = 6;
+const TAG_EXPIRATION_TIMER: u8 = 7;
+
+/// Kind of a [`Content::Receipt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReceiptKind {
+    Delivery,
+    Read,
+}
+
+/// Action of a [`Content::Typing`] indicator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypingAction {
+    Started,
+    Stopped,
+}
+
+/// Typed payload carried inside an encrypted pairwise message.
+///
+/// Earlier messages are referred to by the ID of their [`ContentMessage`].
+///
+/// # Variants
+/// - `Text`: A text message.
+/// - `Reaction`: An emoji reaction to the message `target_id`; `remove` withdraws a
+///   previous reaction.
+/// - `Receipt`: Delivery or read receipt for the messages `message_ids`.
+/// - `Typing`: Typing indicator.
+/// - `Edit`: Replaces the body of our message `target_id`.
+/// - `Delete`: Deletes our message `target_id` for everyone.
+/// - `EndSession`: Last message of the session; both parties archive it and the next
+///   message starts a new session.
+/// - `ExpirationTimerUpdate`: Sets the disappearing-message timer of the conversation,
+///   in seconds; `0` disables it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Content {
+    Text {
+        body: String,
+    },
+    Reaction {
+        emoji: String,
+        target_id: String,
+        remove: bool,
+    },
+    Receipt {
+        kind: ReceiptKind,
+        message_ids: Vec<String>,
+    },
+    Typing {
+        action: TypingAction,
+    },
+    Edit {
+        target_id: String,
+        body: String,
+    },
+    Delete {
+        target_id: String,
+    },
+    EndSession,
+    ExpirationTimerUpdate {
+        seconds: u32,
+    },
+}
+
+impl Content {
+    /// Builds a [`Content::Text`].
+    pub fn text(body: &str) -> Self {
+        Content::Text {
+            body: body.to_string(),
+        }
+    }
+
+    /// Returns `true` if the recipient is expected to acknowledge this content with
+    /// receipts. Receipts, typing indicators and session ends are never acknowledged.
+    pub fn expects_receipt(&self) -> bool {
+        !matches!(
+            self,
+            Content::Receipt { .. } | Content::Typing { .. } | Content::EndSession
+        )
+    }
+}
+
+/// A [`Content`] together with the identifier and sent timestamp of the message carrying
+/// it. This is the plaintext of the Double Ratchet message.
+///
+/// # Fields
+/// - `id`: UUID uniquely identifying the message, referenced by receipts.
+/// - `timestamp`: Sender's clock when the message was sent, with millisecond precision.
+/// - `expire_timer`: Disappearing-message timer of the conversation when the message was
+///   sent, in seconds; `0` if messages do not expire.
+/// - `content`: The payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentMessage {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub expire_timer: u32,
+    pub content: Content,
+}
+
+impl ContentMessage {
+    /// Wraps `content` into a message with a fresh ID, sent now.
+    ///
+    /// # Arguments
+    /// - `content`: The payload.
+    /// - `expire_timer`: Disappearing-message timer in seconds, `0` to disable.
+    pub fn new(content: Content, expire_timer: u32) -> Self {
+        Self::new_with_rng(content, expire_timer, &mut OsRng)
+    }
+
+    /// Wraps `content` into a message sent now, drawing its ID from the given RNG.
+    ///
+    /// # Arguments
+    /// - `content`: The payload.
+    /// - `expire_timer`: Disappearing-message timer in seconds, `0` to disable.
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible message IDs.
+    pub fn new_with_rng<R: CryptoRng + RngCore>(
+        content: Content,
+        expire_timer: u32,
+        rng: &mut R,
+    ) -> Self {
+        let now = Utc::now().timestamp_millis();
+        Self {
+            id: random_uuid(rng),
+            timestamp: DateTime::from_timestamp_millis(now).unwrap(),
+            expire_timer,
+            content,
+        }
+    }
+
+    /// Returns when the message disappears.
+    ///
+    /// The expiry is carried by the message itself, counting from its sent timestamp, so
+    /// that the sender and the recipient purge it at the same time.
+    ///
+    /// # Returns
+    /// The sent timestamp plus the expiry timer, or `None` if the message does not expire
+    /// or the expiry is past the latest representable time. [`ContentMessage::decode`]
+    /// rejects such messages.
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        (self.expire_timer > 0)
+            .then(|| {
+                self.timestamp
+                    .checked_add_signed(TimeDelta::seconds(i64::from(self.expire_timer)))
+            })
+            .flatten()
+    }
+
+    /// Encodes the message into its versioned binary form.
+    ///
+    /// The encoding is a version byte, the message ID, timestamp and expiry timer, a content
+    /// tag, then the content's fields in declaration order: strings and lists as a
+    /// big-endian `u32` length followed by their items, timestamps as big-endian `i64`
+    /// milliseconds, flags and enums as one byte.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![CONTENT_VERSION];
+        put_str(&mut bytes, &self.id);
+        put_time(&mut bytes, &self.timestamp);
+        bytes.extend_from_slice(&self.expire_timer.to_be_bytes());
+        match &self.content {
+            Content::Text { body } => {
+                bytes.push(TAG_TEXT);
+                put_str(&mut bytes, body);
+            }
+            Content::Reaction {
+                emoji,
+                target_id,
+                remove,
+            } => {
+                bytes.push(TAG_REACTION);
+                put_str(&mut bytes, emoji);
+                put_str(&mut bytes, target_id);
+                bytes.push(*remove as u8);
+            }
+            Content::Receipt { kind, message_ids } => {
+                bytes.push(TAG_RECEIPT);
+                bytes.push(match kind {
+                    ReceiptKind::Delivery => 0,
+                    ReceiptKind::Read => 1,
+                });
+                bytes.extend_from_slice(&(message_ids.len() as u32).to_be_bytes());
+                for id in message_ids {
+                    put_str(&mut bytes, id);
+                }
+            }
+            Content::Typing { action } => {
+                bytes.push(TAG_TYPING);
+                bytes.push(match action {
+                    TypingAction::Started => 0,
+                    TypingAction::Stopped => 1,
+                });
+            }
+            Content::Edit { target_id, body } => {
+                bytes.push(TAG_EDIT);
+                put_str(&mut bytes, target_id);
+                put_str(&mut bytes, body);
+            }
+            Content::Delete { target_id } => {
+                bytes.push(TAG_DELETE);
+                put_str(&mut bytes, target_id);
+            }
+            Content::EndSession => bytes.push(TAG_END_SESSION),
+            Content::ExpirationTimerUpdate { seconds } => {
+                bytes.push(TAG_EXPIRATION_TIMER);
+                bytes.extend_from_slice(&seconds.to_be_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Decodes a message produced by [`ContentMessage::encode`].
+    ///
+    /// # Returns
+    /// The decoded message, or `None` if the version is unknown, the encoding is malformed,
+    /// trailing bytes remain or the message expires past the latest representable time.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+        if reader.u8()? != CONTENT_VERSION {
+            return None;
+        }
+        let id = reader.string()?;
+        let timestamp = reader.time()?;
+        let expire_timer = reader.u32()?;
+
+        let content = match reader.u8()? {
+            TAG_TEXT => Content::Text {
+                body: reader.string()?,
+            },
+            TAG_REACTION => Content::Reaction {
+                emoji: reader.string()?,
+                target_id: reader.string()?,
+                remove: reader.bool()?,
+            },
+            TAG_RECEIPT => {
+                let kind = match reader.u8()? {
+                    0 => ReceiptKind::Delivery,
+                    1 => ReceiptKind::Read,
+                    _ => return None,
+                };
+                let count = reader.u32()? as usize;
+                let message_ids = (0..count)
+                    .map(|_| reader.string())
+                    .collect::<Option<Vec<_>>>()?;
+                Content::Receipt { kind, message_ids }
+            }
+            TAG_TYPING => Content::Typing {
+                action: match reader.u8()? {
+                    0 => TypingAction::Started,
+                    1 => TypingAction::Stopped,
+                    _ => return None,
+                },
+            },
+            TAG_EDIT => Content::Edit {
+                target_id: reader.string()?,
+                body: reader.string()?,
+            },
+            TAG_DELETE => Content::Delete {
+                target_id: reader.string()?,
+            },
+            TAG_END_SESSION => Content::EndSession,
+            TAG_EXPIRATION_TIMER => Content::ExpirationTimerUpdate {
+                seconds: reader.u32()?,
+            },
+            _ => return None,
+        };
+
+        let message = Self {
+            id,
+            timestamp,
+            expire_timer,
+            content,
+        };
+        let expiry_valid = expire_timer == 0 || message.expires_at().is_some();
+        (reader.0.is_empty() && expiry_valid).then_some(message)
+    }
+}
+
+/// Delivery progress of a message we sent, as reported by the recipient's receipts.
+///
+/// States are ordered: a late delivery receipt never downgrades a message already read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DeliveryState {
+    Sent,
+    Delivered,
+    Read,
+}
+
+/// A message we sent and are awaiting receipts for.
+///
+/// # Fields
+/// - `recipient_id`: User ID of the recipient; only its receipts are accepted.
+/// - `timestamp`: Sent timestamp of the message.
+/// - `state`: Delivery state reported so far.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentMessage {
+    pub recipient_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub state: DeliveryState,
+}
+
+/// A [`ContentMessage`] received over a pairwise session, with its sender.
+///
+/// # Fields
+/// - `sender_id`: Sender's user ID.
+/// - `sender_name`: Sender's display name.
+/// - `sender_identity_key`: Sender's X25519 identity public key.
+/// - `message_id`: ID of the message, to reference in receipts.
+/// - `timestamp`: Sender's timestamp of the message.
+/// - `expires_at`: When the message disappears, or `None` if it does not expire.
+/// - `content`: The decoded content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceivedContent {
+    pub sender_id: String,
+    pub sender_name: String,
+    pub sender_identity_key: [u8; 32],
+    pub message_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub content: Content,
+}
+
+/// A disappearing message due for deletion from the message store.
+///
+/// # Fields
+/// - `peer_id`: User ID of the other party of the conversation.
+/// - `message_id`: ID of the message.
+/// - `expires_at`: When the message must be purged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiringMessage {
+    pub peer_id: String,
+    pub message_id: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn put_str(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+fn put_time(bytes: &mut Vec<u8>, value: &DateTime<Utc>) {
+    bytes.extend_from_slice(&value.timestamp_millis().to_be_bytes());
+}
+
+/// Cursor over an encoded [`ContentMessage`].
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.0.split_first_chunk::<N>()?;
+        self.0 = rest;
+        Some(*head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take::<1>().map(|[b]| b)
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_be_bytes)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        String::from_utf8(head.to_vec()).ok()
+    }
+
+    fn time(&mut self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(i64::from_be_bytes(self.take()?))
+    }
+}
@@ -0,0 +1,419 @@
+use aes::Aes256;
+use aes_gcm::Aes256Gcm;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit, block_padding::Pkcs7};
+use chacha20poly1305::{
+    XChaCha20Poly1305, XNonce,
+    aead::{Aead, KeyInit, Payload},
+};
+use hkdf::{
+    Hkdf,
+    hmac::{Hmac, Mac},
+};
+use rand_core::CryptoRngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Sha512};
+
+use crate::crypto_utils::{
+    dh::diffie_hellman,
+    encryption::{decrypt_chacha20_aad, encrypt_chacha20_aad},
+    signal_kdf::message_keys,
+};
+
+/// The primitives a session is built on: an AEAD, a Diffie-Hellman function and a KDF.
+///
+/// Suites are identified on the wire by [`CipherSuiteId`]; sessions store the ID and
+/// resolve it with [`CipherSuiteId::suite`].
+pub trait CipherSuite: Sync {
+    /// Returns the identifier of this suite.
+    fn id(&self) -> CipherSuiteId;
+
+    /// Returns the length of the AEAD nonce, or 0 for suites that derive it from the key.
+    fn nonce_len(&self) -> usize;
+
+    /// Encrypts `plaintext` under `key` and `nonce`, authenticating `aad`.
+    ///
+    /// `nonce` must be [`CipherSuite::nonce_len`] bytes long and never reused with `key`.
+    fn encrypt_with_nonce(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Vec<u8>;
+
+    /// Decrypts and authenticates a ciphertext produced by [`CipherSuite::encrypt`] or
+    /// [`CipherSuite::encrypt_with_nonce`].
+    ///
+    /// # Returns
+    /// The plaintext, or `None` if the nonce is malformed or authentication fails.
+    fn decrypt(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Option<Vec<u8>>;
+
+    /// Encrypts `plaintext` under a key used many times, with a nonce drawn from `rng`.
+    ///
+    /// # Returns
+    /// A tuple `(ciphertext, nonce)`.
+    fn encrypt(
+        &self,
+        key: &[u8; 32],
+        plaintext: &[u8],
+        aad: &[u8],
+        rng: &mut dyn CryptoRngCore,
+    ) -> (Vec<u8>, Vec<u8>) {
+        let mut nonce = vec![0u8; self.nonce_len()];
+        rng.fill_bytes(&mut nonce);
+        let ciphertext = self.encrypt_with_nonce(key, &nonce, plaintext, aad);
+        (ciphertext, nonce)
+    }
+
+    /// Derives the AEAD key and nonce protecting the message of a single-use message key.
+    ///
+    /// # Returns
+    /// A tuple `(key, nonce)` expanded from `message_key` with the suite's KDF.
+    fn message_key_nonce(&self, message_key: &[u8; 32]) -> ([u8; 32], Vec<u8>) {
+        let mut okm = vec![0u8; 32 + self.nonce_len()];
+        self.kdf(None, message_key, b"message-key-nonce", &mut okm);
+        let (key, nonce) = okm.split_at(32);
+        (key.try_into().unwrap(), nonce.to_vec())
+    }
+
+    /// Encrypts a message under a single-use message key. No nonce is transmitted.
+    fn encrypt_message(&self, message_key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        let (key, nonce) = self.message_key_nonce(message_key);
+        self.encrypt_with_nonce(&key, &nonce, plaintext, aad)
+    }
+
+    /// Decrypts a message produced by [`CipherSuite::encrypt_message`].
+    ///
+    /// # Returns
+    /// The plaintext, or `None` if authentication fails.
+    fn decrypt_message(
+        &self,
+        message_key: &[u8; 32],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Option<Vec<u8>> {
+        let (key, nonce) = self.message_key_nonce(message_key);
+        self.decrypt(&key, &nonce, ciphertext, aad)
+    }
+
+    /// Computes a Diffie-Hellman shared secret.
+    fn dh(&self, private: &[u8; 32], public: &[u8; 32]) -> [u8; 32] {
+        diffie_hellman(private, public)
+    }
+
+    /// Fills `okm` with HKDF output for the given salt, input keying material and label.
+    fn kdf(&self, salt: Option<&[u8]>, ikm: &[u8], info: &[u8], okm: &mut [u8]);
+}
+
+/// Identifier of a [`CipherSuite`], advertised in bundles and carried by sessions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CipherSuiteId {
+    /// ChaCha20-Poly1305, X25519, HKDF-SHA256. Supported by every client.
+    #[default]
+    ChaCha20Poly1305Sha256,
+    /// AES-256-GCM, X25519, HKDF-SHA256.
+    Aes256GcmSha256,
+    /// XChaCha20-Poly1305, X25519, HKDF-SHA512.
+    XChaCha20Poly1305Sha512,
+    /// Signal message crypto: AES-256-CBC/PKCS7 with a truncated HMAC-SHA256, X25519,
+    /// HKDF-SHA256.
+    SignalAes256CbcHmacSha256,
+}
+
+impl CipherSuiteId {
+    /// All suites implemented by this crate, in order of preference.
+    pub const ALL: [CipherSuiteId; 4] = [
+        CipherSuiteId::ChaCha20Poly1305Sha256,
+        CipherSuiteId::Aes256GcmSha256,
+        CipherSuiteId::XChaCha20Poly1305Sha512,
+        CipherSuiteId::SignalAes256CbcHmacSha256,
+    ];
+
+    /// Returns the implementation of this suite.
+    pub fn suite(self) -> &'static dyn CipherSuite {
+        match self {
+            CipherSuiteId::ChaCha20Poly1305Sha256 => &ChaCha20Poly1305Sha256,
+            CipherSuiteId::Aes256GcmSha256 => &Aes256GcmSha256,
+            CipherSuiteId::XChaCha20Poly1305Sha512 => &XChaCha20Poly1305Sha512,
+            CipherSuiteId::SignalAes256CbcHmacSha256 => &SignalAes256CbcHmacSha256,
+        }
+    }
+
+    /// Returns the suite used to encrypt message headers.
+    ///
+    /// Header keys encrypt many headers, so they need a randomized AEAD; the Signal suite,
+    /// whose IV is derived from the (single-use) key, falls back to the default suite.
+    pub fn header_suite(self) -> &'static dyn CipherSuite {
+        match self {
+            CipherSuiteId::SignalAes256CbcHmacSha256 => CipherSuiteId::default().suite(),
+            _ => self.suite(),
+        }
+    }
+
+    /// Picks the suite of a new session.
+    ///
+    /// # Arguments
+    /// - `preferred`: The initiator's preferred suite.
+    /// - `supported`: Suites advertised by the responder.
+    ///
+    /// # Returns
+    /// `preferred` if the responder supports it, the default suite otherwise.
+    pub fn negotiate(preferred: CipherSuiteId, supported: &[CipherSuiteId]) -> CipherSuiteId {
+        if supported.contains(&preferred) {
+            preferred
+        } else {
+            CipherSuiteId::default()
+        }
+    }
+}
+
+/// ChaCha20-Poly1305 with HKDF-SHA256: the original suite of this crate.
+struct ChaCha20Poly1305Sha256;
+
+impl CipherSuite for ChaCha20Poly1305Sha256 {
+    fn id(&self) -> CipherSuiteId {
+        CipherSuiteId::ChaCha20Poly1305Sha256
+    }
+
+    fn nonce_len(&self) -> usize {
+        12
+    }
+
+    fn encrypt_with_nonce(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Vec<u8> {
+        encrypt_chacha20_aad(key, nonce.try_into().unwrap(), plaintext, aad)
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Option<Vec<u8>> {
+        let nonce: [u8; 12] = nonce.try_into().ok()?;
+        decrypt_chacha20_aad(key, &nonce, ciphertext, aad).ok()
+    }
+
+    fn kdf(&self, salt: Option<&[u8]>, ikm: &[u8], info: &[u8], okm: &mut [u8]) {
+        Hkdf::<Sha256>::new(salt, ikm)
+            .expand(info, okm)
+            .expect("HKDF expand failed");
+    }
+}
+
+/// AES-256-GCM with HKDF-SHA256.
+struct Aes256GcmSha256;
+
+impl CipherSuite for Aes256GcmSha256 {
+    fn id(&self) -> CipherSuiteId {
+        CipherSuiteId::Aes256GcmSha256
+    }
+
+    fn nonce_len(&self) -> usize {
+        12
+    }
+
+    fn encrypt_with_nonce(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Vec<u8> {
+        let cipher = Aes256Gcm::new(key.into());
+        cipher
+            .encrypt(
+                aes_gcm::Nonce::from_slice(nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("encryption failure!")
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Option<Vec<u8>> {
+        if nonce.len() != 12 {
+            return None;
+        }
+        let cipher = Aes256Gcm::new(key.into());
+        cipher
+            .decrypt(
+                aes_gcm::Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .ok()
+    }
+
+    fn kdf(&self, salt: Option<&[u8]>, ikm: &[u8], info: &[u8], okm: &mut [u8]) {
+        Hkdf::<Sha256>::new(salt, ikm)
+            .expand(info, okm)
+            .expect("HKDF expand failed");
+    }
+}
+
+/// XChaCha20-Poly1305 (192-bit random nonces) with HKDF-SHA512.
+struct XChaCha20Poly1305Sha512;
+
+impl CipherSuite for XChaCha20Poly1305Sha512 {
+    fn id(&self) -> CipherSuiteId {
+        CipherSuiteId::XChaCha20Poly1305Sha512
+    }
+
+    fn nonce_len(&self) -> usize {
+        24
+    }
+
+    fn encrypt_with_nonce(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Vec<u8> {
+        let cipher = XChaCha20Poly1305::new(key.into());
+        cipher
+            .encrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("encryption failure!")
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Option<Vec<u8>> {
+        if nonce.len() != 24 {
+            return None;
+        }
+        let cipher = XChaCha20Poly1305::new(key.into());
+        cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .ok()
+    }
+
+    fn kdf(&self, salt: Option<&[u8]>, ikm: &[u8], info: &[u8], okm: &mut [u8]) {
+        Hkdf::<Sha512>::new(salt, ikm)
+            .expand(info, okm)
+            .expect("HKDF expand failed");
+    }
+}
+
+/// Length of the truncated MAC appended by the Signal suite.
+pub(crate) const SIGNAL_MAC_LEN: usize = 8;
+
+/// Signal message crypto, for interoperability with Signal-format clients.
+///
+/// Each message key is expanded with HKDF-SHA256 (`"WhisperMessageKeys"`) into an AES-256
+/// key, an HMAC-SHA256 key and a CBC IV. The body is AES-256-CBC/PKCS7 encrypted, then
+/// `HMAC(mac_key, aad || ciphertext)` truncated to [`SIGNAL_MAC_LEN`] bytes is appended.
+/// The ratchet passes the version byte, both identity keys and the serialized header as
+/// `aad`, matching the MAC input of Signal messages.
+///
+/// Keys must never be reused: the IV is a function of the key.
+struct SignalAes256CbcHmacSha256;
+
+impl SignalAes256CbcHmacSha256 {
+    fn mac(mac_key: &[u8; 32], aad: &[u8], ciphertext: &[u8]) -> Hmac<Sha256> {
+        let mut hmac = <Hmac<Sha256> as Mac>::new_from_slice(mac_key).unwrap();
+        hmac.update(aad);
+        hmac.update(ciphertext);
+        hmac
+    }
+}
+
+impl CipherSuite for SignalAes256CbcHmacSha256 {
+    fn id(&self) -> CipherSuiteId {
+        CipherSuiteId::SignalAes256CbcHmacSha256
+    }
+
+    fn nonce_len(&self) -> usize {
+        0
+    }
+
+    fn encrypt_with_nonce(
+        &self,
+        key: &[u8; 32],
+        _nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Vec<u8> {
+        let (cipher_key, mac_key, iv) = message_keys(key);
+        let mut ciphertext = cbc::Encryptor::<Aes256>::new(&cipher_key.into(), &iv.into())
+            .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+
+        let mac = Self::mac(&mac_key, aad, &ciphertext)
+            .finalize()
+            .into_bytes();
+        ciphertext.extend_from_slice(&mac[..SIGNAL_MAC_LEN]);
+        ciphertext
+    }
+
+    /// The message key is used as is: its `"WhisperMessageKeys"` expansion already
+    /// yields the IV, as in libsignal.
+    fn message_key_nonce(&self, message_key: &[u8; 32]) -> ([u8; 32], Vec<u8>) {
+        (*message_key, Vec::new())
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8; 32],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Option<Vec<u8>> {
+        if !nonce.is_empty() || ciphertext.len() < SIGNAL_MAC_LEN {
+            return None;
+        }
+        let (body, mac) = ciphertext.split_at(ciphertext.len() - SIGNAL_MAC_LEN);
+
+        let (cipher_key, mac_key, iv) = message_keys(key);
+        Self::mac(&mac_key, aad, body)
+            .verify_truncated_left(mac)
+            .ok()?;
+
+        cbc::Decryptor::<Aes256>::new(&cipher_key.into(), &iv.into())
+            .decrypt_padded_vec_mut::<Pkcs7>(body)
+            .ok()
+    }
+
+    fn kdf(&self, salt: Option<&[u8]>, ikm: &[u8], info: &[u8], okm: &mut [u8]) {
+        Hkdf::<Sha256>::new(salt, ikm)
+            .expand(info, okm)
+            .expect("HKDF expand failed");
+    }
+}
@@ -1,8 +1,8 @@
 use chacha20poly1305::{
     ChaCha20Poly1305, Key, Nonce,
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit, OsRng, Payload},
 };
-use rand_core::RngCore;
+use rand_core::{CryptoRng, RngCore};
 
 /// Encrypts a message using ChaCha20-Poly1305 with a random nonce.
 ///
@@ -18,18 +18,69 @@ use rand_core::RngCore;
 /// # Panics
 /// Panics if encryption fails (should never occur with valid input sizes).
 pub(crate) fn encrypt_chacha20(key_bytes: &[u8; 32], plaintext: &[u8]) -> (Vec<u8>, [u8; 12]) {
-    let key = Key::from_slice(key_bytes);
-    let cipher = ChaCha20Poly1305::new(key);
+    encrypt_chacha20_with_rng(key_bytes, plaintext, &mut OsRng)
+}
 
-    let mut nonce_bytes = [0u8; 12];
-    OsRng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
+/// Encrypts a message using ChaCha20-Poly1305 with a nonce drawn from the given RNG.
+///
+/// # Parameters
+/// - `key_bytes`: A 32-byte symmetric encryption key.
+/// - `plaintext`: The message to encrypt.
+/// - `rng`: Source of the nonce. A seeded RNG yields reproducible ciphertexts.
+///
+/// # Returns
+/// A tuple `(ciphertext, nonce)`.
+///
+/// # Panics
+/// Panics if encryption fails (should never occur with valid input sizes).
+pub(crate) fn encrypt_chacha20_with_rng<R: CryptoRng + RngCore>(
+    key_bytes: &[u8; 32],
+    plaintext: &[u8],
+    rng: &mut R,
+) -> (Vec<u8>, [u8; 12]) {
+    let mut nonce = [0u8; 12];
+    rng.fill_bytes(&mut nonce);
+    (
+        encrypt_chacha20_aad(key_bytes, &nonce, plaintext, &[]),
+        nonce,
+    )
+}
 
-    let ciphertext = cipher
-        .encrypt(nonce, plaintext)
-        .expect("encryption failure!");
+/// Encrypts a message using ChaCha20-Poly1305 with the given nonce and associated data.
+///
+/// The associated data is authenticated but not encrypted; decryption fails unless the
+/// exact same bytes are supplied to [`decrypt_chacha20_aad`]. The caller must never reuse
+/// a nonce under the same key.
+///
+/// # Parameters
+/// - `key_bytes`: A 32-byte symmetric encryption key.
+/// - `nonce_bytes`: The 12-byte nonce.
+/// - `plaintext`: The message to encrypt.
+/// - `aad`: Associated data to authenticate.
+///
+/// # Returns
+/// The encrypted and authenticated output.
+///
+/// # Panics
+/// Panics if encryption fails (should never occur with valid input sizes).
+pub(crate) fn encrypt_chacha20_aad(
+    key_bytes: &[u8; 32],
+    nonce_bytes: &[u8; 12],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Vec<u8> {
+    let key = Key::from_slice(key_bytes);
+    let cipher = ChaCha20Poly1305::new(key);
 
-    (ciphertext, nonce_bytes)
+    cipher
+        .encrypt(
+            Nonce::from_slice(nonce_bytes),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("encryption failure!")
 }
 
 /// Decrypts a ciphertext using ChaCha20-Poly1305.
@@ -46,10 +97,36 @@ pub(crate) fn decrypt_chacha20(
     key_bytes: &[u8; 32],
     nonce_bytes: &[u8; 12],
     ciphertext: &[u8],
+) -> Result<Vec<u8>, chacha20poly1305::aead::Error> {
+    decrypt_chacha20_aad(key_bytes, nonce_bytes, ciphertext, &[])
+}
+
+/// Decrypts a ciphertext using ChaCha20-Poly1305, authenticating associated data.
+///
+/// # Parameters
+/// - `key_bytes`: A 32-byte symmetric encryption key.
+/// - `nonce_bytes`: The 12-byte nonce used during encryption.
+/// - `ciphertext`: The encrypted and authenticated message.
+/// - `aad`: Associated data supplied at encryption time.
+///
+/// # Returns
+/// - `Ok(plaintext)` if decryption and authentication succeed.
+/// - `Err(_)` otherwise.
+pub(crate) fn decrypt_chacha20_aad(
+    key_bytes: &[u8; 32],
+    nonce_bytes: &[u8; 12],
+    ciphertext: &[u8],
+    aad: &[u8],
 ) -> Result<Vec<u8>, chacha20poly1305::aead::Error> {
     let key = Key::from_slice(key_bytes);
     let cipher = ChaCha20Poly1305::new(key);
 
     let nonce = Nonce::from_slice(nonce_bytes);
-    cipher.decrypt(nonce, ciphertext)
+    cipher.decrypt(
+        nonce,
+        Payload {
+            msg: ciphertext,
+            aad,
+        },
+    )
 }
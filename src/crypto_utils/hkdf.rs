@@ -22,6 +22,28 @@ pub(crate) fn derive_session_key(dh_results: &[u8]) -> [u8; 32] {
     okm
 }
 
+/// Derives a 32-byte secret with HKDF-SHA256 for a given context label.
+///
+/// General-purpose helper for protocols that need labelled derivations
+/// (group key schedules, sealed boxes, ...).
+///
+/// # Parameters
+/// - `salt`: Optional HKDF salt.
+/// - `ikm`: Input keying material.
+/// - `info`: Context label binding the output to its purpose.
+///
+/// # Returns
+/// A 32-byte derived secret.
+///
+/// # Panics
+/// Panics if HKDF expansion fails (should not happen with 32-byte output).
+pub(crate) fn derive_secret(salt: Option<&[u8]>, ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let hk = Hkdf::<Sha256>::new(salt, ikm);
+    let mut okm = [0u8; 32];
+    hk.expand(info, &mut okm).expect("HKDF expand failed");
+    okm
+}
+
 /// Derives a new root key from a session key using HKDF.
 ///
 /// This is the first step in initializing or updating the root key in a Double Ratchet state.
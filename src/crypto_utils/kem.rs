@@ -0,0 +1,60 @@
+use ml_kem::{
+    Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768,
+    kem::{Decapsulate, Encapsulate},
+};
+use rand_core::{CryptoRng, RngCore};
+
+type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// Generates a fresh ML-KEM-768 key pair.
+///
+/// # Parameters
+/// - `rng`: Source of randomness.
+///
+/// # Returns
+/// A tuple `(decapsulation_key, encapsulation_key)` of encoded keys.
+pub(crate) fn kem_keypair<R: CryptoRng + RngCore>(rng: &mut R) -> (Vec<u8>, Vec<u8>) {
+    let (dk, ek) = MlKem768::generate(rng);
+    (dk.as_bytes().to_vec(), ek.as_bytes().to_vec())
+}
+
+/// Encapsulates a fresh shared secret to an ML-KEM-768 encapsulation key.
+///
+/// # Parameters
+/// - `encapsulation_key`: The recipient's encoded encapsulation key.
+/// - `rng`: Source of randomness.
+///
+/// # Returns
+/// - `Some((ciphertext, shared_secret))` on success
+/// - `None` if the key is malformed
+pub(crate) fn kem_encapsulate<R: CryptoRng + RngCore>(
+    encapsulation_key: &[u8],
+    rng: &mut R,
+) -> Option<(Vec<u8>, [u8; 32])> {
+    let encoded = Encoded::<EncapsulationKey>::try_from(encapsulation_key).ok()?;
+    let ek = EncapsulationKey::from_bytes(&encoded);
+    let (ciphertext, shared) = ek.encapsulate(rng).ok()?;
+    Some((ciphertext.to_vec(), shared.into()))
+}
+
+/// Recovers the shared secret from an ML-KEM-768 ciphertext.
+///
+/// # Parameters
+/// - `decapsulation_key`: Our encoded decapsulation key.
+/// - `ciphertext`: The ciphertext produced by [`kem_encapsulate`].
+///
+/// # Returns
+/// - `Some(shared_secret)` if the inputs are well-formed
+/// - `None` otherwise
+///
+/// # Security
+/// ML-KEM decapsulation never fails on a well-formed but forged ciphertext: it returns
+/// an unrelated secret instead, so tampering surfaces as a failure to decrypt.
+pub(crate) fn kem_decapsulate(decapsulation_key: &[u8], ciphertext: &[u8]) -> Option<[u8; 32]> {
+    let encoded = Encoded::<DecapsulationKey>::try_from(decapsulation_key).ok()?;
+    let dk = DecapsulationKey::from_bytes(&encoded);
+    let ciphertext = Ciphertext::<MlKem768>::try_from(ciphertext).ok()?;
+    let shared = dk.decapsulate(&ciphertext).ok()?;
+    Some(shared.into())
+}
@@ -1,3 +1,9 @@
+pub mod cipher_suite;
 pub mod dh;
 pub mod encryption;
 pub mod hkdf;
+pub mod kem;
+pub mod padding;
+pub mod rng;
+pub mod seal;
+pub mod signal_kdf;
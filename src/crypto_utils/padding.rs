@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/// Marker byte appended to the plaintext before the zero padding (ISO/IEC 7816-4).
+const PADDING_MARKER: u8 = 0x80;
+
+/// Block size of [`Padding::Signal160`], in bytes.
+const SIGNAL_BLOCK_SIZE: usize = 160;
+
+/// Padding applied to message plaintexts before encryption, so that ciphertext lengths
+/// leak less about plaintext lengths.
+///
+/// Padded plaintexts end with a `0x80` marker followed by zero bytes, which makes
+/// unpadding unambiguous whatever the plaintext content.
+///
+/// - `None`: No padding; the ciphertext length reveals the exact plaintext length.
+/// - `Signal160`: Pads to the next multiple of 160 bytes, as Signal clients do.
+/// - `Padme`: Padmé padding, which pads to a length whose low bits are zero so that at
+///   most `O(log log L)` bits of the length `L` leak, with at most 12% overhead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Padding {
+    #[default]
+    None,
+    Signal160,
+    Padme,
+}
+
+impl Padding {
+    /// Pads a plaintext according to this scheme.
+    ///
+    /// # Arguments
+    /// - `plaintext`: Bytes to pad.
+    ///
+    /// # Returns
+    /// The padded plaintext, ready to be encrypted.
+    pub fn pad(&self, plaintext: &[u8]) -> Vec<u8> {
+        let unpadded_len = plaintext.len() + 1;
+        let padded_len = match self {
+            Padding::None => return plaintext.to_vec(),
+            Padding::Signal160 => unpadded_len.div_ceil(SIGNAL_BLOCK_SIZE) * SIGNAL_BLOCK_SIZE,
+            Padding::Padme => padme_len(unpadded_len),
+        };
+
+        let mut padded = Vec::with_capacity(padded_len);
+        padded.extend_from_slice(plaintext);
+        padded.push(PADDING_MARKER);
+        padded.resize(padded_len, 0);
+        padded
+    }
+
+    /// Removes the padding added by [`Padding::pad`].
+    ///
+    /// # Arguments
+    /// - `padded`: Decrypted, padded plaintext.
+    ///
+    /// # Returns
+    /// The original plaintext, or `None` if the padding is malformed.
+    pub fn unpad(&self, padded: &[u8]) -> Option<Vec<u8>> {
+        if *self == Padding::None {
+            return Some(padded.to_vec());
+        }
+
+        let marker = padded.iter().rposition(|&b| b != 0)?;
+        (padded[marker] == PADDING_MARKER).then(|| padded[..marker].to_vec())
+    }
+}
+
+/// Computes the Padmé length of `len`: `len` rounded up so that only its
+/// `floor(log2(floor(log2(len)))) + 1` most significant bits may be set.
+fn padme_len(len: usize) -> usize {
+    if len < 2 {
+        return len;
+    }
+    let exponent = len.ilog2();
+    let significant_bits = exponent.ilog2() + 1;
+    let mask = (1usize << (exponent - significant_bits)) - 1;
+    (len + mask) & !mask
+}
@@ -0,0 +1,18 @@
+use rand_core::{CryptoRng, RngCore};
+
+/// Generates a random (version 4) UUID string from the given RNG.
+///
+/// Used for key and user identifiers, so that a seeded RNG also reproduces them.
+///
+/// # Parameters
+/// - `rng`: Source of randomness.
+///
+/// # Returns
+/// The hyphenated UUID string.
+pub(crate) fn random_uuid<R: CryptoRng + RngCore>(rng: &mut R) -> String {
+    let mut bytes = [0u8; 16];
+    rng.fill_bytes(&mut bytes);
+    uuid::Builder::from_random_bytes(bytes)
+        .into_uuid()
+        .to_string()
+}
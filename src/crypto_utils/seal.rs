@@ -0,0 +1,102 @@
+use rand_core::{CryptoRng, OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::crypto_utils::{
+    dh::diffie_hellman,
+    encryption::{decrypt_chacha20, encrypt_chacha20_with_rng},
+    hkdf::derive_secret,
+};
+use crate::keys::ephemeral_key::EphemeralKey;
+
+/// A payload encrypted to an X25519 public key with a one-shot ephemeral key.
+///
+/// Only the holder of the matching private key can open it; the sender stays anonymous
+/// unless the plaintext itself identifies it.
+///
+/// # Fields
+/// - `ephemeral_public`: Sender's ephemeral X25519 public key.
+/// - `nonce`: A 12-byte nonce for AEAD encryption.
+/// - `ciphertext`: The encrypted and authenticated payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedBox {
+    pub ephemeral_public: [u8; 32],
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+/// Encrypts `plaintext` to `recipient_public` (ephemeral X25519 + HKDF + ChaCha20-Poly1305).
+///
+/// The AEAD key is derived from `DH(ephemeral, recipient)` using both public keys as salt
+/// and `info` as context label, so a box sealed for one purpose cannot be opened for another.
+///
+/// # Parameters
+/// - `recipient_public`: Recipient's X25519 public key.
+/// - `info`: Context label.
+/// - `plaintext`: Payload to encrypt.
+///
+/// # Returns
+/// A [`SealedBox`] containing the ephemeral public key, nonce and ciphertext.
+pub(crate) fn seal(recipient_public: &[u8; 32], info: &[u8], plaintext: &[u8]) -> SealedBox {
+    seal_with_rng(recipient_public, info, plaintext, &mut OsRng)
+}
+
+/// Encrypts `plaintext` to `recipient_public`, drawing the ephemeral key and nonce from
+/// the given RNG.
+///
+/// # Parameters
+/// - `recipient_public`: Recipient's X25519 public key.
+/// - `info`: Context label.
+/// - `plaintext`: Payload to encrypt.
+/// - `rng`: Source of randomness. A seeded RNG yields reproducible boxes.
+///
+/// # Returns
+/// A [`SealedBox`] containing the ephemeral public key, nonce and ciphertext.
+pub(crate) fn seal_with_rng<R: CryptoRng + RngCore>(
+    recipient_public: &[u8; 32],
+    info: &[u8],
+    plaintext: &[u8],
+    rng: &mut R,
+) -> SealedBox {
+    let ek = EphemeralKey::new_with_rng(rng);
+    let shared = diffie_hellman(&ek.get_private(), recipient_public);
+    let key = derive_secret(
+        Some(&[ek.public, *recipient_public].concat()),
+        &shared,
+        info,
+    );
+
+    let (ciphertext, nonce) = encrypt_chacha20_with_rng(&key, plaintext, rng);
+
+    SealedBox {
+        ephemeral_public: ek.public,
+        nonce,
+        ciphertext,
+    }
+}
+
+/// Opens a [`SealedBox`] with the recipient's X25519 private key.
+///
+/// # Parameters
+/// - `recipient_private`: Recipient's X25519 private key.
+/// - `info`: Context label used when sealing.
+/// - `sealed`: The box to open.
+///
+/// # Returns
+/// - `Some(plaintext)` if the box was sealed to this key with the same label
+/// - `None` otherwise
+pub(crate) fn open(
+    recipient_private: &[u8; 32],
+    info: &[u8],
+    sealed: &SealedBox,
+) -> Option<Vec<u8>> {
+    let recipient_public = PublicKey::from(&StaticSecret::from(*recipient_private));
+    let shared = diffie_hellman(recipient_private, &sealed.ephemeral_public);
+    let key = derive_secret(
+        Some(&[sealed.ephemeral_public, *recipient_public.as_bytes()].concat()),
+        &shared,
+        info,
+    );
+
+    decrypt_chacha20(&key, &sealed.nonce, &sealed.ciphertext).ok()
+}
@@ -0,0 +1,106 @@
+use hkdf::Hkdf;
+use hkdf::hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Key derivation constants used by a session.
+///
+/// `Default` keeps this crate's own HKDF labels. `Signal` switches the X3DH, root and
+/// chain KDFs to the constants of libsignal (protocol version 3), so that session keys
+/// derived from the same DH outputs match a Signal client. Combined with the
+/// [`SignalAes256CbcHmacSha256`](crate::crypto_utils::cipher_suite::CipherSuiteId)
+/// cipher suite, the derived message keys match too.
+///
+/// In `Signal` mode all KDFs are HKDF-SHA256 / HMAC-SHA256, whatever the KDF of the
+/// negotiated cipher suite.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KdfMode {
+    #[default]
+    Default,
+    Signal,
+}
+
+/// Prefix of the X3DH key material, distinguishing it from the XEdDSA signature domain.
+const DISCONTINUITY_BYTES: [u8; 32] = [0xFF; 32];
+
+/// HMAC input deriving a message key seed from a chain key.
+const MESSAGE_KEY_SEED: u8 = 0x01;
+
+/// HMAC input deriving the next chain key from a chain key.
+const CHAIN_KEY_SEED: u8 = 0x02;
+
+/// Splits a 64-byte KDF output into a root key and a chain key.
+fn split(okm: &[u8; 64]) -> ([u8; 32], [u8; 32]) {
+    (okm[..32].try_into().unwrap(), okm[32..].try_into().unwrap())
+}
+
+/// Derives the initial root and chain keys of a session from the X3DH DH outputs.
+///
+/// Computes `HKDF-SHA256(salt = 0^32, ikm = 0xFF^32 || dh_outputs, info = "WhisperText")`
+/// over 64 bytes.
+///
+/// # Parameters
+/// - `dh_outputs`: Concatenated DH outputs (DH1 || DH2 || DH3 [|| DH4]), in X3DH order.
+///
+/// # Returns
+/// A tuple `(root_key, chain_key)`.
+pub fn x3dh_keys(dh_outputs: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let mut ikm = DISCONTINUITY_BYTES.to_vec();
+    ikm.extend_from_slice(dh_outputs);
+
+    let mut okm = [0u8; 64];
+    Hkdf::<Sha256>::new(Some(&[0u8; 32]), &ikm)
+        .expand(b"WhisperText", &mut okm)
+        .expect("HKDF expand failed");
+    split(&okm)
+}
+
+/// Performs a Double Ratchet root KDF step.
+///
+/// Computes `HKDF-SHA256(salt = root_key, ikm = dh_output, info = "WhisperRatchet")`
+/// over 64 bytes.
+///
+/// # Returns
+/// A tuple `(next_root_key, chain_key)`.
+pub fn root_step(root_key: &[u8; 32], dh_output: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+    let mut okm = [0u8; 64];
+    Hkdf::<Sha256>::new(Some(root_key), dh_output)
+        .expand(b"WhisperRatchet", &mut okm)
+        .expect("HKDF expand failed");
+    split(&okm)
+}
+
+/// Performs a symmetric chain KDF step.
+///
+/// - The message key seed is `HMAC-SHA256(chain_key, 0x01)`
+/// - The next chain key is `HMAC-SHA256(chain_key, 0x02)`
+///
+/// # Returns
+/// A tuple `(next_chain_key, message_key_seed)`.
+pub fn chain_step(chain_key: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+    let hmac = |seed: u8| -> [u8; 32] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(chain_key).unwrap();
+        mac.update(&[seed]);
+        mac.finalize().into_bytes().into()
+    };
+    (hmac(CHAIN_KEY_SEED), hmac(MESSAGE_KEY_SEED))
+}
+
+/// Expands a message key seed into the keys protecting one message.
+///
+/// Computes `HKDF-SHA256(salt = none, ikm = seed, info = "WhisperMessageKeys")` over
+/// 80 bytes.
+///
+/// # Returns
+/// A tuple `(cipher_key, mac_key, iv)`.
+pub fn message_keys(seed: &[u8; 32]) -> ([u8; 32], [u8; 32], [u8; 16]) {
+    let mut okm = [0u8; 80];
+    Hkdf::<Sha256>::new(None, seed)
+        .expand(b"WhisperMessageKeys", &mut okm)
+        .expect("HKDF expand failed");
+
+    let cipher_key = okm[..32].try_into().unwrap();
+    let mac_key = okm[32..64].try_into().unwrap();
+    let iv = okm[64..].try_into().unwrap();
+    (cipher_key, mac_key, iv)
+}
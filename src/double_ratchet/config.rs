@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto_utils::{cipher_suite::CipherSuiteId, padding::Padding, signal_kdf::KdfMode};
+
+/// Options negotiated when a Double Ratchet session is created.
+///
+/// The initiator picks the configuration and attaches it to its prekey messages; the
+/// responder adopts it when it builds the session from the first message.
+///
+/// # Fields
+/// - `header_encryption`: Encrypt message headers (ratchet public key and message index)
+///   with header keys derived from the root chain, so that they are hidden from the relay.
+/// - `pqxdh`: Establish the session with PQXDH, mixing an ML-KEM-768 shared secret into
+///   the X3DH key material.
+/// - `pq_ratchet`: Run the sparse post-quantum ratchet alongside the Double Ratchet,
+///   mixing its epoch keys into message keys.
+/// - `cipher_suite`: AEAD, DH and KDF used by the ratchet. When set on a user, this is
+///   the preferred suite, negotiated against the suites advertised by the peer.
+/// - `kdf_mode`: Constants of the X3DH, root and chain KDFs. [`KdfMode::Signal`] derives
+///   the same keys as libsignal.
+/// - `padding`: Padding applied to message plaintexts before encryption, hiding their
+///   exact length from the relay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionConfig {
+    pub header_encryption: bool,
+    pub pqxdh: bool,
+    pub pq_ratchet: bool,
+    pub cipher_suite: CipherSuiteId,
+    pub kdf_mode: KdfMode,
+    pub padding: Padding,
+}
+
+impl SessionConfig {
+    /// Serializes the configuration as associated data for the prekey messages carrying it,
+    /// so that a relay cannot alter the options the responder adopts.
+    pub(crate) fn to_bytes(self) -> [u8; 6] {
+        [
+            self.header_encryption as u8,
+            self.pqxdh as u8,
+            self.pq_ratchet as u8,
+            self.cipher_suite as u8,
+            self.kdf_mode as u8,
+            self.padding as u8,
+        ]
+    }
+}
+
+/// Retention policy of the message keys skipped over by out-of-order delivery.
+///
+/// Skipped keys of messages that never arrive weaken forward secrecy, so they are purged
+/// by [`User::prune_skipped_keys`](crate::User::prune_skipped_keys) once either limit is
+/// exceeded. This is a local policy and is not negotiated with the peer.
+///
+/// # Fields
+/// - `max_age`: How long a skipped key is kept after it was stored; `None` for no limit.
+/// - `max_ratchet_steps`: How many receiving DH ratchet steps a skipped key survives;
+///   `None` for no limit.
+///
+/// The default keeps keys for 30 days and 5 ratchet steps, the number of receiving
+/// chains libsignal retains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedKeyPolicy {
+    pub max_age: Option<Duration>,
+    pub max_ratchet_steps: Option<u32>,
+}
+
+impl Default for SkippedKeyPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            max_ratchet_steps: Some(5),
+        }
+    }
+}
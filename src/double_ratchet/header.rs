@@ -0,0 +1,79 @@
+use rand_core::CryptoRngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::{crypto_utils::cipher_suite::CipherSuite, keys::encrypted_message::EncryptedHeader};
+
+/// Header keys of a session using header encryption.
+///
+/// Every root KDF step yields a "next header key" alongside the new chain key. It encrypts
+/// the headers of the chain created by the following root step, which lets the receiver
+/// recognise a new ratchet public key before it performs the DH step.
+///
+/// # Fields
+/// - `sending`: Header key of the current sending chain.
+/// - `receiving`: Header key of the current receiving chain.
+/// - `next`: Header key of the next chain to be created, in either direction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct HeaderKeys {
+    pub(crate) sending: Option<[u8; 32]>,
+    pub(crate) receiving: Option<[u8; 32]>,
+    pub(crate) next: [u8; 32],
+}
+
+impl HeaderKeys {
+    /// Creates the header keys of a fresh session from the initial next header key.
+    pub(crate) fn new(next: [u8; 32]) -> Self {
+        Self {
+            sending: None,
+            receiving: None,
+            next,
+        }
+    }
+}
+
+/// Encrypts a message header.
+///
+/// # Parameters
+/// - `suite`: Cipher suite of the session.
+/// - `header_key`: Header key of the sending chain.
+/// - `ratchet_pub`: Sender's current ratchet public key.
+/// - `message_index`: Index of the message in the sending chain.
+/// - `previous_counter`: Length of the previous sending chain.
+/// - `rng`: Source of the nonce.
+pub(crate) fn encrypt_header(
+    suite: &dyn CipherSuite,
+    header_key: &[u8; 32],
+    ratchet_pub: &[u8; 32],
+    message_index: u32,
+    previous_counter: u32,
+    rng: &mut dyn CryptoRngCore,
+) -> EncryptedHeader {
+    let mut plaintext = ratchet_pub.to_vec();
+    plaintext.extend_from_slice(&message_index.to_be_bytes());
+    plaintext.extend_from_slice(&previous_counter.to_be_bytes());
+
+    let (ciphertext, nonce) = suite.encrypt(header_key, &plaintext, &[], rng);
+    EncryptedHeader { nonce, ciphertext }
+}
+
+/// Attempts to decrypt a message header with `header_key` under the session's suite.
+///
+/// # Returns
+/// - `Some((ratchet_pub, message_index, previous_counter))` if the header was encrypted
+///   under `header_key`
+/// - `None` otherwise
+pub(crate) fn decrypt_header(
+    suite: &dyn CipherSuite,
+    header_key: &[u8; 32],
+    header: &EncryptedHeader,
+) -> Option<([u8; 32], u32, u32)> {
+    let plaintext = suite.decrypt(header_key, &header.nonce, &header.ciphertext, &[])?;
+    if plaintext.len() != 40 {
+        return None;
+    }
+
+    let ratchet_pub: [u8; 32] = plaintext[..32].try_into().ok()?;
+    let message_index = u32::from_be_bytes(plaintext[32..36].try_into().ok()?);
+    let previous_counter = u32::from_be_bytes(plaintext[36..].try_into().ok()?);
+    Some((ratchet_pub, message_index, previous_counter))
+}
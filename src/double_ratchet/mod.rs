@@ -1 +1,5 @@
+pub mod config;
+pub mod header;
+pub mod pq_ratchet;
+pub mod session_info;
 pub mod state;
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypto_utils::{
+        hkdf::derive_secret,
+        kem::{kem_decapsulate, kem_encapsulate, kem_keypair},
+    },
+    keys::encrypted_message::{PqChunk, PqChunkKind, PqRatchetHeader},
+};
+
+/// Size of the key material fragments attached to messages.
+pub(crate) const PQ_CHUNK_SIZE: usize = 128;
+
+/// Number of established epoch keys kept for late messages.
+pub(crate) const MAX_PQ_EPOCHS: usize = 4;
+
+/// Encoded sizes of ML-KEM-768 encapsulation keys and ciphertexts.
+const ENCAPSULATION_KEY_SIZE: usize = 1184;
+const CIPHERTEXT_SIZE: usize = 1088;
+
+/// Our role in the negotiation of the current PQ epoch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum PqPhase {
+    /// We hold the key of this epoch but have not generated it yet (done on next send).
+    PendingKeygen,
+    /// We stream our encapsulation key and collect the peer's ciphertext.
+    SendingKey {
+        decapsulation_key: Vec<u8>,
+        encapsulation_key: Vec<u8>,
+        ciphertext_chunks: BTreeMap<u16, Vec<u8>>,
+    },
+    /// We collect the peer's encapsulation key.
+    AwaitingKey { chunks: BTreeMap<u16, Vec<u8>> },
+    /// We have the peer's encapsulation key and encapsulate on next send.
+    KeyReceived { encapsulation_key: Vec<u8> },
+    /// We stream our ciphertext until the peer proves it derived the epoch key.
+    SendingCiphertext { ciphertext: Vec<u8> },
+}
+
+/// A sparse, KEM-based post-quantum ratchet running alongside the Double Ratchet.
+///
+/// The parties take turns: in each epoch one of them streams a fresh ML-KEM-768
+/// encapsulation key in [`PQ_CHUNK_SIZE`] fragments attached to its messages, the other
+/// encapsulates to it and streams back the ciphertext. The resulting shared secret is
+/// chained into a new epoch key, which is mixed into every message key. A compromise of
+/// the session state is healed, even against a quantum adversary, once an epoch completes.
+///
+/// Fragments are sent round-robin until the phase changes, so lost messages only delay
+/// the epoch. They are authenticated as associated data of the message they ride on.
+///
+/// # Fields
+/// - `epoch`: Epoch currently being negotiated.
+/// - `send_epoch`: Latest epoch known to be established on both sides.
+/// - `keys`: Established epoch keys, indexed by epoch.
+/// - `phase`: Our role in the negotiation of `epoch`.
+/// - `cursor`: Index of the next fragment to send.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PqRatchet {
+    epoch: u64,
+    send_epoch: u64,
+    keys: BTreeMap<u64, [u8; 32]>,
+    phase: PqPhase,
+    cursor: u16,
+}
+
+/// Number of fragments of a key material of `size` bytes.
+fn chunk_count(size: usize) -> u16 {
+    size.div_ceil(PQ_CHUNK_SIZE) as u16
+}
+
+/// Stores a fragment and returns the reassembled material once all fragments arrived.
+fn collect_chunk(
+    chunks: &mut BTreeMap<u16, Vec<u8>>,
+    chunk: &PqChunk,
+    size: usize,
+) -> Option<Vec<u8>> {
+    let total = chunk_count(size);
+    if chunk.total != total || chunk.index >= total {
+        return None;
+    }
+    chunks.insert(chunk.index, chunk.data.clone());
+
+    if chunks.len() < total as usize {
+        return None;
+    }
+    let material = chunks.values().flatten().copied().collect::<Vec<u8>>();
+    (material.len() == size).then_some(material)
+}
+
+impl PqRatchet {
+    /// Creates the PQ ratchet of a new session.
+    ///
+    /// # Arguments
+    /// - `root_key`: Initial root key, seeding epoch 0.
+    /// - `is_initiator`: The initiator streams the first encapsulation key.
+    pub(crate) fn new(root_key: &[u8; 32], is_initiator: bool) -> Self {
+        let mut keys = BTreeMap::new();
+        keys.insert(0, derive_secret(None, root_key, b"pq-ratchet-init"));
+
+        let phase = if is_initiator {
+            PqPhase::PendingKeygen
+        } else {
+            PqPhase::AwaitingKey {
+                chunks: BTreeMap::new(),
+            }
+        };
+
+        Self {
+            epoch: 1,
+            send_epoch: 0,
+            keys,
+            phase,
+            cursor: 0,
+        }
+    }
+
+    /// Mixes the key of `epoch` into a Double Ratchet message key.
+    ///
+    /// # Returns
+    /// The message key to use, or `None` if the epoch key is unknown or was pruned.
+    pub(crate) fn message_key(&self, epoch: u64, chain_message_key: &[u8; 32]) -> Option<[u8; 32]> {
+        let pq_key = self.keys.get(&epoch)?;
+        Some(derive_secret(
+            Some(pq_key),
+            chain_message_key,
+            b"pq-ratchet-message-key",
+        ))
+    }
+
+    /// Prepares the PQ data of an outgoing message.
+    ///
+    /// Performs any pending key generation or encapsulation with `rng`, then picks the
+    /// next fragment to send.
+    pub(crate) fn prepare_send<R: CryptoRng + RngCore>(&mut self, rng: &mut R) -> PqRatchetHeader {
+        match &self.phase {
+            PqPhase::PendingKeygen => {
+                let (decapsulation_key, encapsulation_key) = kem_keypair(rng);
+                self.phase = PqPhase::SendingKey {
+                    decapsulation_key,
+                    encapsulation_key,
+                    ciphertext_chunks: BTreeMap::new(),
+                };
+                self.cursor = 0;
+            }
+            PqPhase::KeyReceived { encapsulation_key } => {
+                self.phase = match kem_encapsulate(encapsulation_key, rng) {
+                    Some((ciphertext, shared_secret)) => {
+                        self.establish(&shared_secret);
+                        PqPhase::SendingCiphertext { ciphertext }
+                    }
+                    None => PqPhase::AwaitingKey {
+                        chunks: BTreeMap::new(),
+                    },
+                };
+                self.cursor = 0;
+            }
+            _ => {}
+        }
+
+        let fragment = match &self.phase {
+            PqPhase::SendingKey {
+                encapsulation_key, ..
+            } => Some((PqChunkKind::EncapsulationKey, encapsulation_key)),
+            PqPhase::SendingCiphertext { ciphertext } => {
+                Some((PqChunkKind::Ciphertext, ciphertext))
+            }
+            _ => None,
+        };
+
+        let chunk = fragment.map(|(kind, material)| {
+            let total = chunk_count(material.len());
+            let index = self.cursor % total;
+            self.cursor = self.cursor.wrapping_add(1);
+
+            let start = index as usize * PQ_CHUNK_SIZE;
+            let end = (start + PQ_CHUNK_SIZE).min(material.len());
+            PqChunk {
+                epoch: self.epoch,
+                kind,
+                index,
+                total,
+                data: material[start..end].to_vec(),
+            }
+        });
+
+        PqRatchetHeader {
+            epoch: self.send_epoch,
+            chunk,
+        }
+    }
+
+    /// Processes the PQ data of an authenticated incoming message.
+    pub(crate) fn receive(&mut self, header: &PqRatchetHeader) {
+        if matches!(self.phase, PqPhase::SendingCiphertext { .. }) && header.epoch >= self.epoch {
+            // The peer uses the epoch we encapsulated: it derived the key too.
+            self.advance(PqPhase::PendingKeygen);
+        }
+
+        let Some(chunk) = header.chunk.as_ref().filter(|c| c.epoch == self.epoch) else {
+            return;
+        };
+
+        match (&mut self.phase, chunk.kind) {
+            (PqPhase::AwaitingKey { chunks }, PqChunkKind::EncapsulationKey) => {
+                if let Some(encapsulation_key) =
+                    collect_chunk(chunks, chunk, ENCAPSULATION_KEY_SIZE)
+                {
+                    self.phase = PqPhase::KeyReceived { encapsulation_key };
+                }
+            }
+            (
+                PqPhase::SendingKey {
+                    decapsulation_key,
+                    ciphertext_chunks,
+                    ..
+                },
+                PqChunkKind::Ciphertext,
+            ) => {
+                let shared_secret = collect_chunk(ciphertext_chunks, chunk, CIPHERTEXT_SIZE)
+                    .and_then(|ciphertext| kem_decapsulate(decapsulation_key, &ciphertext));
+                if let Some(shared_secret) = shared_secret {
+                    self.establish(&shared_secret);
+                    self.advance(PqPhase::AwaitingKey {
+                        chunks: BTreeMap::new(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Derives the key of the epoch being negotiated from its KEM shared secret.
+    fn establish(&mut self, shared_secret: &[u8; 32]) {
+        let previous = self.keys.values().next_back().copied().unwrap_or_default();
+        let key = derive_secret(Some(&previous), shared_secret, b"pq-ratchet-epoch");
+        self.keys.insert(self.epoch, key);
+
+        while self.keys.len() > MAX_PQ_EPOCHS {
+            self.keys.pop_first();
+        }
+    }
+
+    /// Starts using the established epoch for sending and moves to the next epoch.
+    fn advance(&mut self, phase: PqPhase) {
+        self.send_epoch = self.epoch;
+        self.epoch += 1;
+        self.phase = phase;
+        self.cursor = 0;
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto_utils::cipher_suite::CipherSuiteId;
+
+/// Secret-free summary of a Double Ratchet session, for diagnostics.
+///
+/// Unlike the `Display` output of the session state, it exposes no key material.
+///
+/// # Fields
+/// - `peer_identity_key`: The peer's X25519 identity public key.
+/// - `created_at`: When the session was established locally.
+/// - `ratchet_steps`: Number of DH ratchet steps performed on receiving a new ratchet key
+///   from the peer.
+/// - `sending_index`: Index of the next message key of the sending chain.
+/// - `receiving_index`: Index of the next message key of the receiving chain.
+/// - `skipped_keys`: Number of stored skipped message keys.
+/// - `x3dh_pending`: Whether we initiated the session and have not received any message
+///   in it yet, so the peer has not confirmed the X3DH handshake.
+/// - `cipher_suite`: Cipher suite negotiated for the session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub peer_identity_key: [u8; 32],
+    pub created_at: DateTime<Utc>,
+    pub ratchet_steps: u32,
+    pub sending_index: u32,
+    pub receiving_index: u32,
+    pub skipped_keys: usize,
+    pub x3dh_pending: bool,
+    pub cipher_suite: CipherSuiteId,
+}
+
+impl SessionInfo {
+    /// Returns the age of the session at `now`.
+    pub fn age(&self, now: DateTime<Utc>) -> TimeDelta {
+        now - self.created_at
+    }
+}
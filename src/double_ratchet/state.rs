@@ -1,174 +1,618 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Display,
+};
 
-use hkdf::Hkdf;
+use chrono::{DateTime, TimeDelta, Utc};
+use rand_core::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
 
 use crate::{
     crypto_utils::{
-        dh::diffie_hellman,
-        encryption::{decrypt_chacha20, encrypt_chacha20},
+        cipher_suite::CipherSuite,
+        hkdf::{derive_initial_chain_keys, derive_root_key, derive_secret},
+        signal_kdf::{self, KdfMode},
+    },
+    double_ratchet::{
+        config::{SessionConfig, SkippedKeyPolicy},
+        header::{HeaderKeys, decrypt_header, encrypt_header},
+        pq_ratchet::PqRatchet,
+        session_info::SessionInfo,
     },
+    error::DecryptError,
     keys::{
         chain_key::ChainKey, encrypted_message::EncryptedMessage, message_key::MessageKey,
-        ratchet_key::RatchetKey, root_key::RootKey,
+        ratchet_key::RatchetKey, root_key::RootKey, session_key::SessionKey,
     },
 };
 
+/// Header fields recovered from a received message.
+///
+/// `chain_id` identifies the receiving chain in the skipped-key store: the sender's
+/// ratchet public key, or the chain's header key when headers are encrypted.
+/// `previous_counter` is the length of the sender's previous sending chain.
+struct ReceivedHeader {
+    ratchet_pub: [u8; 32],
+    message_index: u32,
+    previous_counter: u32,
+    chain_id: Vec<u8>,
+    new_chain: bool,
+}
+
+/// A message key skipped over while catching up a receiving chain.
+///
+/// `ratchet_step` is the number of receiving DH ratchet steps the session had performed
+/// when the key was stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SkippedKey {
+    key: MessageKey,
+    stored_at: DateTime<Utc>,
+    ratchet_step: u32,
+}
+
+/// Maximum number of message keys a receiver will skip over in one chain to cope with
+/// out-of-order messages.
+pub(crate) const MAX_SKIP: u32 = 2000;
+
+/// Number of past receiving chains remembered to recognize duplicate messages.
+const MAX_PREVIOUS_CHAINS: usize = 32;
+
+/// Version byte of the message format, authenticated with every message.
+const MESSAGE_VERSION: u8 = 3;
+
+/// Type prefix of X25519 public keys in the Signal wire format.
+const DJB_KEY_TYPE: u8 = 0x05;
+
+/// Builds the associated data authenticated with a message body.
+///
+/// Follows the layout of the Signal MAC input: sender identity key, receiver identity key,
+/// then the serialized message header (version, ratchet public key, index, previous chain
+/// length, encrypted header and PQ data) and, for prekey messages, the session options.
+fn associated_data(
+    sender_identity: &[u8; 32],
+    receiver_identity: &[u8; 32],
+    msg: &EncryptedMessage,
+) -> Vec<u8> {
+    let mut aad = Vec::new();
+    aad.push(DJB_KEY_TYPE);
+    aad.extend_from_slice(sender_identity);
+    aad.push(DJB_KEY_TYPE);
+    aad.extend_from_slice(receiver_identity);
+    aad.push(MESSAGE_VERSION);
+    aad.extend_from_slice(&msg.ratchet_pub);
+    aad.extend_from_slice(&msg.message_index.to_be_bytes());
+    aad.extend_from_slice(&msg.previous_counter.to_be_bytes());
+    if let Some(header) = &msg.header {
+        aad.extend_from_slice(&header.to_bytes());
+    }
+    if let Some(pq) = &msg.pq {
+        aad.extend_from_slice(&pq.to_bytes());
+    }
+    if let Some(config) = &msg.session_config {
+        aad.extend_from_slice(&config.to_bytes());
+    }
+    aad
+}
+
 /// Maintains the sender/receiver cryptographic state in a Double Ratchet session.
 ///
 /// `RatchetState` manages key evolution and encryption/decryption operations between
 /// two parties. It tracks chain keys, DH keys, and message indexes while implementing
 /// skipped message handling for out-of-order delivery.
+///
+/// When the session is configured with header encryption, `header_keys` holds the header
+/// keys of the current chains and skipped message keys are indexed by header key instead
+/// of ratchet public key. When it is configured with the PQ ratchet, `pq_ratchet` runs
+/// alongside and its epoch keys are mixed into every message key.
+///
+/// Skipped message keys record when and at which receiving ratchet step they were stored,
+/// so that [`RatchetState::prune`] can purge them under a [`SkippedKeyPolicy`].
+///
+/// `previous_sending_index` is the length of our previous sending chain, sent with every
+/// message so the peer can store the keys of the messages of that chain it has not
+/// received yet before it performs its next DH ratchet step.
+///
+/// `previous_chains` remembers the IDs and final lengths of past receiving chains, most
+/// recent last. Their unreceived message keys are in the skipped-key store, so any other
+/// message from one of them below its final length was already consumed and is reported
+/// as a duplicate instead of triggering a DH ratchet step.
+///
+/// `base_key` is the initiator's X3DH ephemeral public key, which identifies the session
+/// when a peer has several of them (current and archived).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct RatchetState {
+    config: SessionConfig,
+    local_identity: [u8; 32],
+    remote_identity: [u8; 32],
     root_key: RootKey,
     sending_chain: ChainKey,
+    previous_sending_index: u32,
     receiving_chain: ChainKey,
     dhs: RatchetKey,
     dhr: Option<[u8; 32]>,
     last_dhr: Option<[u8; 32]>,
-    skipped_message_keys: HashMap<(Vec<u8>, u32), MessageKey>,
+    skipped_message_keys: HashMap<(Vec<u8>, u32), SkippedKey>,
+    ratchet_steps: u32,
+    previous_chains: VecDeque<(Vec<u8>, u32)>,
+    header_keys: Option<HeaderKeys>,
+    pq_ratchet: Option<PqRatchet>,
+    base_key: Option<[u8; 32]>,
+    created_at: DateTime<Utc>,
 }
 
 impl RatchetState {
-    /// Initializes a new `RatchetState` with a given root key and DH key material.
+    /// Initializes a new `RatchetState` from an X3DH session key and DH key material.
+    ///
+    /// In [`KdfMode::Signal`], the session key is the root key and both initial chains
+    /// start from the chain key derived with it, as the responder's sending chain and the
+    /// initiator's receiving chain do in libsignal.
     ///
     /// # Arguments
-    /// - `root_key`: Initial shared root key
+    /// - `session`: Session key agreed with X3DH
     /// - `dhs`: Our current DH private/public key pair
     /// - `dhr`: Their current public key (if known)
     /// - `is_initiator`: Whether we are the session initiator (affects chain ordering)
+    /// - `config`: Session options agreed with the peer
+    /// - `identities`: Our and their identity public keys, authenticated with every message
     pub(crate) fn new(
-        root_key: RootKey,
+        session: &SessionKey,
         dhs: RatchetKey,
         dhr: Option<[u8; 32]>,
         is_initiator: bool,
+        config: SessionConfig,
+        identities: ([u8; 32], [u8; 32]),
     ) -> Self {
-        let (sending_chain, receiving_chain) = if is_initiator {
-            crate::crypto_utils::hkdf::derive_initial_chain_keys(&root_key)
-        } else {
-            let (recv, send) = crate::crypto_utils::hkdf::derive_initial_chain_keys(&root_key);
-            (send, recv)
+        let (root_key, sending_chain, receiving_chain) = match session.get_chain_key() {
+            Some(chain_key) if config.kdf_mode == KdfMode::Signal => (
+                RootKey::new(*session.get_bytes()),
+                ChainKey::new(*chain_key, 0),
+                ChainKey::new(*chain_key, 0),
+            ),
+            _ => {
+                let root_key = derive_root_key(session.get_bytes());
+                let (first, second) = derive_initial_chain_keys(&root_key);
+                if is_initiator {
+                    (root_key, first, second)
+                } else {
+                    (root_key, second, first)
+                }
+            }
         };
+        let header_keys = config.header_encryption.then(|| {
+            HeaderKeys::new(derive_secret(
+                None,
+                root_key.get_bytes(),
+                b"double-ratchet-initial-hk",
+            ))
+        });
+        let pq_ratchet = config
+            .pq_ratchet
+            .then(|| PqRatchet::new(root_key.get_bytes(), is_initiator));
+        let (local_identity, remote_identity) = identities;
         Self {
+            config,
+            local_identity,
+            remote_identity,
             root_key,
             sending_chain,
+            previous_sending_index: 0,
             receiving_chain,
             dhs,
             dhr,
             last_dhr: None,
             skipped_message_keys: HashMap::new(),
+            ratchet_steps: 0,
+            previous_chains: VecDeque::new(),
+            header_keys,
+            pq_ratchet,
+            base_key: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Records the X3DH ephemeral public key the session was established with.
+    ///
+    /// # Returns
+    /// The `RatchetState` identified by `base_key`.
+    pub(crate) fn with_base_key(mut self, base_key: [u8; 32]) -> Self {
+        self.base_key = Some(base_key);
+        self
+    }
+
+    /// Returns the X3DH ephemeral public key the session was established with, if known.
+    pub(crate) fn base_key(&self) -> Option<[u8; 32]> {
+        self.base_key
+    }
+
+    /// Summarizes the session without exposing any key material.
+    pub(crate) fn info(&self) -> SessionInfo {
+        SessionInfo {
+            peer_identity_key: self.remote_identity,
+            created_at: self.created_at,
+            ratchet_steps: self.ratchet_steps,
+            sending_index: self.sending_chain.get_index(),
+            receiving_index: self.receiving_chain.get_index(),
+            skipped_keys: self.skipped_message_keys.len(),
+            x3dh_pending: self.ratchet_steps == 0,
+            cipher_suite: self.config.cipher_suite,
+        }
+    }
+
+    /// Returns the cipher suite of this session.
+    fn suite(&self) -> &'static dyn CipherSuite {
+        self.config.cipher_suite.suite()
+    }
+
+    /// Returns the cipher suite protecting message headers.
+    fn header_suite(&self) -> &'static dyn CipherSuite {
+        self.config.cipher_suite.header_suite()
+    }
+
+    /// Performs a root KDF step with a fresh DH output.
+    ///
+    /// With header encryption, the step also yields a new next header key; the one it
+    /// replaces becomes the header key of the chain created by this step.
+    ///
+    /// # Returns
+    /// The new chain key and, with header encryption, its header key.
+    fn root_step(&mut self, dh_output: &[u8; 32]) -> (ChainKey, Option<[u8; 32]>) {
+        let suite = self.suite();
+        let salt = *self.root_key.get_bytes();
+
+        let (rk, ck) = match self.config.kdf_mode {
+            KdfMode::Default => {
+                let mut rk = [0u8; 32];
+                let mut ck = [0u8; 32];
+                suite.kdf(Some(&salt), dh_output, b"double-ratchet-rk", &mut rk);
+                suite.kdf(Some(&salt), dh_output, b"ratchet-ck-send", &mut ck);
+                (rk, ck)
+            }
+            KdfMode::Signal => signal_kdf::root_step(&salt, dh_output),
+        };
+
+        let header_key = self.header_keys.as_mut().map(|keys| {
+            let mut nhk = [0u8; 32];
+            suite.kdf(Some(&salt), dh_output, b"double-ratchet-nhk", &mut nhk);
+            std::mem::replace(&mut keys.next, nhk)
+        });
+
+        self.root_key = RootKey::new(rk);
+        (ChainKey::new(ck, 0), header_key)
+    }
+
+    /// Recovers the header of a received message.
+    ///
+    /// Encrypted headers are tried against the current receiving header key, then the
+    /// next header key (which signals a new ratchet public key), then the header keys of
+    /// past receiving chains and of chains with skipped message keys.
+    fn read_header(&self, msg: &EncryptedMessage) -> Option<ReceivedHeader> {
+        let (keys, header) = match (&self.header_keys, &msg.header) {
+            (None, None) => {
+                return Some(ReceivedHeader {
+                    ratchet_pub: msg.ratchet_pub,
+                    message_index: msg.message_index,
+                    previous_counter: msg.previous_counter,
+                    chain_id: msg.ratchet_pub.to_vec(),
+                    new_chain: self.dhr != Some(msg.ratchet_pub),
+                });
+            }
+            (Some(keys), Some(header)) => (keys, header),
+            _ => return None,
+        };
+
+        let opened = |header_key: &[u8; 32], new_chain: bool| {
+            decrypt_header(self.header_suite(), header_key, header).map(
+                |(ratchet_pub, message_index, previous_counter)| ReceivedHeader {
+                    ratchet_pub,
+                    message_index,
+                    previous_counter,
+                    chain_id: header_key.to_vec(),
+                    new_chain,
+                },
+            )
+        };
+
+        if let Some(received) = keys.receiving.and_then(|hkr| opened(&hkr, false)) {
+            return Some(received);
         }
+        if let Some(received) = opened(&keys.next, true) {
+            return Some(received);
+        }
+
+        let past_chains: HashSet<&Vec<u8>> = self
+            .previous_chains
+            .iter()
+            .map(|(chain_id, _)| chain_id)
+            .chain(
+                self.skipped_message_keys
+                    .keys()
+                    .map(|(chain_id, _)| chain_id),
+            )
+            .collect();
+        past_chains.into_iter().find_map(|chain_id| {
+            let header_key: [u8; 32] = chain_id.as_slice().try_into().ok()?;
+            opened(&header_key, false)
+        })
     }
 
-    /// Encrypts a plaintext message using the next derived message key.
+    /// Returns the ID of the current receiving chain in the skipped-key store, if any.
+    fn receiving_chain_id(&self) -> Option<Vec<u8>> {
+        match &self.header_keys {
+            Some(keys) => keys.receiving.map(|hk| hk.to_vec()),
+            None => self.dhr.map(|dhr| dhr.to_vec()),
+        }
+    }
+
+    /// Encrypts a plaintext payload using the next derived message key.
     ///
     /// Performs a DH ratchet step if `dhr` has changed since the last message.
     ///
     /// # Arguments
-    /// - `plaintext`: Message to encrypt
+    /// - `plaintext`: Payload to encrypt (arbitrary bytes)
     /// - `sender`: Sender name/ID
     /// - `receiver`: Receiver name/ID
     /// - `opk_used`: One-time prekey (if used during X3DH)
     /// - `ek_used`: Ephemeral key used in the session
+    /// - `rng`: Source of the new ratchet keys, PQ ratchet keys and header nonces
     ///
     /// # Returns
     /// An `EncryptedMessage` containing ciphertext and metadata.
-    pub(crate) fn encrypt(
+    pub(crate) fn encrypt<R: CryptoRng + RngCore>(
         &mut self,
-        plaintext: &str,
+        plaintext: &[u8],
         sender: String,
         receiver: String,
         opk_used: Option<[u8; 32]>,
         ek_used: Option<[u8; 32]>,
+        rng: &mut R,
     ) -> EncryptedMessage {
-        let should_ratchet = self.last_dhr.map_or(true, |prev| {
-            self.dhr.map_or(true, |current| current != prev)
-        });
+        let should_ratchet = self.last_dhr.is_none_or(|prev| self.dhr != Some(prev));
 
         if should_ratchet {
             self.last_dhr = self.dhr;
 
-            self.dhs = RatchetKey::new();
-
-            let dh_output = diffie_hellman(&self.dhs.get_private(), self.dhr.as_ref().unwrap());
-
-            let root_hkdf = Hkdf::<Sha256>::new(Some(self.root_key.get_bytes()), &dh_output);
+            self.dhs = RatchetKey::new(rng);
 
-            let mut rk = [0u8; 32];
-            let mut ck_send = [0u8; 32];
-            root_hkdf.expand(b"double-ratchet-rk", &mut rk).unwrap();
-            root_hkdf.expand(b"ratchet-ck-send", &mut ck_send).unwrap();
+            let dh_output = self
+                .suite()
+                .dh(&self.dhs.get_private(), self.dhr.as_ref().unwrap());
 
-            self.root_key = RootKey::new(rk);
-            self.sending_chain = ChainKey::new(ck_send, 0);
+            let (ck_send, header_key) = self.root_step(&dh_output);
+            self.previous_sending_index = self.sending_chain.get_index();
+            self.sending_chain = ck_send;
+            if let Some(keys) = self.header_keys.as_mut() {
+                keys.sending = header_key;
+            }
         }
 
-        let (next_ck, message_key) = self.sending_chain.derive_next();
+        let (next_ck, message_key) = self.sending_chain.derive_next_with(self.config.kdf_mode);
         self.sending_chain = next_ck;
 
-        let (ciphertext, nonce) = encrypt_chacha20(&message_key.get_key(), plaintext.as_bytes());
+        let header = self.header_keys.as_ref().map(|keys| {
+            encrypt_header(
+                self.header_suite(),
+                keys.sending.as_ref().unwrap(),
+                &self.dhs.public,
+                message_key.get_index(),
+                self.previous_sending_index,
+                rng,
+            )
+        });
+        let pq = self.pq_ratchet.as_mut().map(|pq| pq.prepare_send(rng));
 
-        EncryptedMessage {
+        let key = match (&self.pq_ratchet, &pq) {
+            (Some(pq_ratchet), Some(pq)) => pq_ratchet
+                .message_key(pq.epoch, message_key.get_key())
+                .unwrap(),
+            _ => *message_key.get_key(),
+        };
+
+        let (ratchet_pub, message_index, previous_counter) = match header {
+            Some(_) => ([0u8; 32], 0, 0),
+            None => (
+                self.dhs.public,
+                message_key.get_index(),
+                self.previous_sending_index,
+            ),
+        };
+
+        let mut msg = EncryptedMessage {
             sender,
             receiver,
-            ratchet_pub: self.dhs.public,
-            message_index: message_key.get_index(),
-            nonce,
-            ciphertext: ciphertext.to_vec(),
+            ratchet_pub,
+            message_index,
+            previous_counter,
+            header,
+            pq,
+            ciphertext: Vec::new(),
             opk_used,
             ek_used,
-        }
+            kem_prekey_id: None,
+            kem_ciphertext: None,
+            session_config: ek_used.map(|_| self.config),
+        };
+
+        let aad = associated_data(&self.local_identity, &self.remote_identity, &msg);
+        let padded = self.config.padding.pad(plaintext);
+        msg.ciphertext = self.suite().encrypt_message(&key, &padded, &aad);
+        msg
     }
 
     /// Attempts to decrypt a received `EncryptedMessage`.
     ///
-    /// Handles DH ratcheting, skipped message key recovery, and message key derivation.
+    /// Handles header decryption, DH ratcheting, skipped message key recovery, and message
+    /// key derivation.
+    ///
+    /// Decryption runs on a scratch copy of the state, which replaces the session state
+    /// only once the message body has been authenticated and `accept` has recognized the
+    /// payload. A forged, corrupted or duplicate message therefore leaves the session
+    /// unchanged, and so does a payload meant for another receiving API.
     ///
     /// # Returns
-    /// - `Some(plaintext)` if decryption succeeds
-    /// - `None` if decryption fails or the message is malformed
-    pub(crate) fn decrypt(&mut self, msg: &EncryptedMessage) -> Option<String> {
-        let key_id = (msg.ratchet_pub.to_vec(), msg.message_index);
-
-        if let Some(message_key) = self.skipped_message_keys.remove(&key_id) {
-            return decrypt_chacha20(&message_key.get_key(), &msg.nonce, &msg.ciphertext)
-                .ok()
-                .and_then(|bytes| String::from_utf8(bytes).ok());
+    /// - `Ok(plaintext)` with the payload bytes if decryption succeeds
+    /// - `Err(DecryptError::DuplicateMessage)` if the message key was already consumed
+    /// - `Err(DecryptError::InvalidMessage)` if decryption fails, the message is malformed
+    ///   or it would skip more than [`MAX_SKIP`] message keys
+    /// - `Err(DecryptError::UnexpectedPayload)` if `accept` rejects the payload
+    pub(crate) fn decrypt(
+        &mut self,
+        msg: &EncryptedMessage,
+        accept: impl Fn(&[u8]) -> bool,
+    ) -> Result<Vec<u8>, DecryptError> {
+        let mut scratch = self.clone();
+        let plaintext = scratch.decrypt_in_place(msg)?;
+        if !accept(&plaintext) {
+            return Err(DecryptError::UnexpectedPayload);
         }
+        *self = scratch;
+        Ok(plaintext)
+    }
 
-        let is_new_dhr = self.dhr.map_or(true, |prev| prev != msg.ratchet_pub);
+    /// Decrypts a message by advancing this state directly, even if decryption fails.
+    ///
+    /// Messages whose key was already consumed, on the current chain or a past one, are
+    /// rejected before any state is modified. Before a DH ratchet step, the keys of the
+    /// messages of the current receiving chain not received yet are stored, up to the
+    /// previous chain length announced by the sender.
+    fn decrypt_in_place(&mut self, msg: &EncryptedMessage) -> Result<Vec<u8>, DecryptError> {
+        let header = self.read_header(msg).ok_or(DecryptError::InvalidMessage)?;
+        if self.pq_ratchet.is_some() != msg.pq.is_some() {
+            return Err(DecryptError::InvalidMessage);
+        }
+        let aad = associated_data(&self.remote_identity, &self.local_identity, msg);
+        let key_id = (header.chain_id.clone(), header.message_index);
 
-        if is_new_dhr {
-            self.dhr = Some(msg.ratchet_pub);
+        if let Some(skipped) = self.skipped_message_keys.remove(&key_id) {
+            return self
+                .decrypt_body(msg, &skipped.key, &aad)
+                .ok_or(DecryptError::InvalidMessage);
+        }
 
-            let dh_output = diffie_hellman(&self.dhs.get_private(), &msg.ratchet_pub);
+        if let Some((_, length)) = self
+            .previous_chains
+            .iter()
+            .find(|(chain_id, _)| *chain_id == header.chain_id)
+        {
+            return Err(if header.message_index < *length {
+                DecryptError::DuplicateMessage
+            } else {
+                DecryptError::InvalidMessage
+            });
+        }
+        if !header.new_chain && header.message_index < self.receiving_chain.get_index() {
+            return Err(DecryptError::DuplicateMessage);
+        }
 
-            let root_hkdf = Hkdf::<Sha256>::new(Some(self.root_key.get_bytes()), &dh_output);
+        if header.new_chain {
+            if let Some(chain_id) = self.receiving_chain_id() {
+                self.skip_message_keys(&chain_id, header.previous_counter)?;
+                if self.previous_chains.len() == MAX_PREVIOUS_CHAINS {
+                    self.previous_chains.pop_front();
+                }
+                self.previous_chains
+                    .push_back((chain_id, self.receiving_chain.get_index()));
+            }
+            self.dhr = Some(header.ratchet_pub);
+            self.ratchet_steps += 1;
 
-            let mut rk = [0u8; 32];
-            let mut ck_recv = [0u8; 32];
-            root_hkdf.expand(b"double-ratchet-rk", &mut rk).unwrap();
-            root_hkdf.expand(b"ratchet-ck-send", &mut ck_recv).unwrap();
+            let dh_output = self
+                .suite()
+                .dh(&self.dhs.get_private(), &header.ratchet_pub);
 
-            self.root_key = RootKey::new(rk);
-            self.receiving_chain = ChainKey::new(ck_recv, 0);
+            let (ck_recv, header_key) = self.root_step(&dh_output);
+            self.receiving_chain = ck_recv;
+            if let Some(keys) = self.header_keys.as_mut() {
+                keys.receiving = header_key;
+            }
         }
 
-        while self.receiving_chain.get_index() < msg.message_index {
-            let (next_ck, skipped_key) = self.receiving_chain.derive_next();
-            let key = (msg.ratchet_pub.to_vec(), self.receiving_chain.get_index());
-            self.skipped_message_keys.insert(key, skipped_key);
+        self.skip_message_keys(&header.chain_id, header.message_index)?;
+
+        let (next_ck, message_key) = self.receiving_chain.derive_next_with(self.config.kdf_mode);
+        self.receiving_chain = next_ck;
+
+        self.decrypt_body(msg, &message_key, &aad)
+            .ok_or(DecryptError::InvalidMessage)
+    }
+
+    /// Advances the receiving chain to `until`, storing the message keys skipped over.
+    ///
+    /// # Arguments
+    /// - `chain_id`: ID of the receiving chain in the skipped-key store.
+    /// - `until`: Index of the next message key to derive.
+    ///
+    /// # Returns
+    /// `Err(DecryptError::InvalidMessage)`, before deriving any key, if more than
+    /// [`MAX_SKIP`] keys would be skipped.
+    fn skip_message_keys(&mut self, chain_id: &[u8], until: u32) -> Result<(), DecryptError> {
+        if until.saturating_sub(self.receiving_chain.get_index()) > MAX_SKIP {
+            return Err(DecryptError::InvalidMessage);
+        }
+        while self.receiving_chain.get_index() < until {
+            let (next_ck, skipped_key) =
+                self.receiving_chain.derive_next_with(self.config.kdf_mode);
+            let key = (chain_id.to_vec(), self.receiving_chain.get_index());
+            self.skipped_message_keys.insert(
+                key,
+                SkippedKey {
+                    key: skipped_key,
+                    stored_at: Utc::now(),
+                    ratchet_step: self.ratchet_steps,
+                },
+            );
             self.receiving_chain = next_ck;
         }
+        Ok(())
+    }
 
-        let (next_ck, message_key) = self.receiving_chain.derive_next();
-        self.receiving_chain = next_ck;
+    /// Purges the skipped message keys that exceed the retention policy.
+    ///
+    /// # Arguments
+    /// - `now`: Current time, against which key ages are measured.
+    /// - `policy`: Maximum age and number of ratchet steps a skipped key may reach.
+    ///
+    /// # Returns
+    /// The number of purged keys.
+    pub(crate) fn prune(&mut self, now: DateTime<Utc>, policy: &SkippedKeyPolicy) -> usize {
+        let max_age = policy
+            .max_age
+            .map(|age| TimeDelta::from_std(age).unwrap_or(TimeDelta::MAX));
+        let ratchet_steps = self.ratchet_steps;
+        let before = self.skipped_message_keys.len();
+
+        self.skipped_message_keys.retain(|_, skipped| {
+            let too_old = max_age.is_some_and(|age| now - skipped.stored_at > age);
+            let too_many_steps = policy
+                .max_ratchet_steps
+                .is_some_and(|steps| ratchet_steps - skipped.ratchet_step > steps);
+            !too_old && !too_many_steps
+        });
+        before - self.skipped_message_keys.len()
+    }
 
-        decrypt_chacha20(&message_key.get_key(), &msg.nonce, &msg.ciphertext)
-            .ok()
-            .and_then(|bytes| String::from_utf8(bytes).ok())
+    /// Decrypts the body of a message with its chain message key.
+    ///
+    /// With the PQ ratchet, the key of the message's PQ epoch is mixed in first, and the
+    /// PQ data of the message is processed once the body has been authenticated.
+    fn decrypt_body(
+        &mut self,
+        msg: &EncryptedMessage,
+        message_key: &MessageKey,
+        aad: &[u8],
+    ) -> Option<Vec<u8>> {
+        let key = match (&self.pq_ratchet, &msg.pq) {
+            (Some(pq_ratchet), Some(pq)) => {
+                pq_ratchet.message_key(pq.epoch, message_key.get_key())?
+            }
+            _ => *message_key.get_key(),
+        };
+
+        let padded = self.suite().decrypt_message(&key, &msg.ciphertext, aad)?;
+        let plaintext = self.config.padding.unpad(&padded)?;
+        if let (Some(pq_ratchet), Some(pq)) = (self.pq_ratchet.as_mut(), &msg.pq) {
+            pq_ratchet.receive(pq);
+        }
+        Some(plaintext)
     }
 }
 
@@ -178,7 +622,8 @@ impl Display for RatchetState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "root_key: {}\nsending_chain: {:?}\nreceiving_chain: {:?}\ndhs.pub: {}\ndhs.priv: {}\ndhr: {}\nlast_dhr: {}\nskipped_message_keys: {}",
+            "header_encryption: {}\nroot_key: {}\nsending_chain: {:?}\nreceiving_chain: {:?}\ndhs.pub: {}\ndhs.priv: {}\ndhr: {}\nlast_dhr: {}\nskipped_message_keys: {}",
+            self.config.header_encryption,
             self.root_key,
             self.sending_chain,
             self.receiving_chain,
@@ -194,12 +639,12 @@ impl Display for RatchetState {
             },
             {
                 let mut skipped = String::new();
-                for ((ratchet_pub, idx), msg_key) in &self.skipped_message_keys {
+                for ((ratchet_pub, idx), skipped_key) in &self.skipped_message_keys {
                     skipped.push_str(&format!(
                         "\n  pub: {}, idx: {}, key: {}",
                         hex::encode(ratchet_pub),
                         idx,
-                        hex::encode(&msg_key.get_key())
+                        hex::encode(skipped_key.key.get_key())
                     ));
                 }
                 skipped
@@ -0,0 +1,38 @@
+use std::fmt::Display;
+
+/// Reasons a pairwise message can fail to decrypt.
+///
+/// - `DuplicateMessage`: The message key was already consumed, or the prekey message
+///   replays the ephemeral key of a session that already exists. Skipped keys purged by
+///   the retention policy are reported the same way.
+/// - `NoSession`: No session exists with the sender and the message does not start one.
+/// - `InvalidPreKeyMessage`: The message starts a session but references an unknown
+///   one-time or KEM pre-key, or carries malformed X3DH fields.
+/// - `InvalidMessage`: The header cannot be read, the message would skip too many message
+///   keys, or the body fails to authenticate or unpad.
+/// - `UnexpectedPayload`: The message decrypts but its payload is not of the type the
+///   receiving API expects. The session is left unchanged, so the message can still be
+///   received with the matching API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecryptError {
+    DuplicateMessage,
+    NoSession,
+    InvalidPreKeyMessage,
+    InvalidMessage,
+    UnexpectedPayload,
+}
+
+impl Display for DecryptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            DecryptError::DuplicateMessage => "duplicate message",
+            DecryptError::NoSession => "no session with the sender",
+            DecryptError::InvalidPreKeyMessage => "invalid prekey message",
+            DecryptError::InvalidMessage => "invalid message",
+            DecryptError::UnexpectedPayload => "unexpected payload type",
+        };
+        f.write_str(reason)
+    }
+}
+
+impl std::error::Error for DecryptError {}
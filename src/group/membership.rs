@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+use crate::keys::{
+    encrypted_message::EncryptedMessage, sender_key_message::SenderKeyDistributionMessage,
+};
+use crate::user::public_info::UserPublicInfo;
+
+/// Leading byte of an encoded [`GroupControlMessage`]. It tells group control payloads
+/// apart from [`ContentMessage`](crate::content::ContentMessage)s sent over the same
+/// pairwise session, whose leading version byte is never zero.
+const GROUP_CONTROL_PAYLOAD: u8 = 0;
+
+/// Role of a member inside a [`Group`].
+///
+/// Only admins may add or remove other members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupRole {
+    Admin,
+    Member,
+}
+
+/// A single group member and its role.
+///
+/// # Fields
+/// - `info`: Public bundle of the member, used to reach it over a pairwise session.
+/// - `role`: The member's role in the group.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMember {
+    pub info: UserPublicInfo,
+    pub role: GroupRole,
+}
+
+/// Local view of a group: its identifier, display name and member list.
+///
+/// The list always includes the local user. It is kept in sync by exchanging
+/// [`GroupChange`]s over the pairwise Double Ratchet sessions.
+///
+/// # Fields
+/// - `id`: Unique group identifier.
+/// - `name`: Human-readable group name.
+/// - `members`: Current members, including ourselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub id: String,
+    pub name: String,
+    members: Vec<GroupMember>,
+}
+
+impl Group {
+    /// Creates a new group from an explicit member list.
+    pub(crate) fn new(id: String, name: String, members: Vec<GroupMember>) -> Self {
+        Self { id, name, members }
+    }
+
+    /// Returns the current members of the group.
+    pub fn members(&self) -> &[GroupMember] {
+        &self.members
+    }
+
+    /// Looks up a member by user ID.
+    pub fn member(&self, user_id: &str) -> Option<&GroupMember> {
+        self.members.iter().find(|m| m.info.id == user_id)
+    }
+
+    /// Returns `true` if `user_id` is a member with the [`GroupRole::Admin`] role.
+    pub fn is_admin(&self, user_id: &str) -> bool {
+        self.member(user_id)
+            .is_some_and(|m| m.role == GroupRole::Admin)
+    }
+
+    /// Adds a member, replacing any existing entry for the same user ID.
+    pub(crate) fn add_member(&mut self, member: GroupMember) {
+        self.remove_member(&member.info.id);
+        self.members.push(member);
+    }
+
+    /// Removes a member by user ID.
+    ///
+    /// # Returns
+    /// `true` if the member was present.
+    pub(crate) fn remove_member(&mut self, user_id: &str) -> bool {
+        let before = self.members.len();
+        self.members.retain(|m| m.info.id != user_id);
+        self.members.len() != before
+    }
+}
+
+/// A membership change propagated to the other members of a group.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GroupChange {
+    /// Full group state, sent by an admin to members joining the group.
+    Create {
+        name: String,
+        members: Vec<GroupMember>,
+    },
+    /// A new member was added by an admin.
+    Add { member: Box<GroupMember> },
+    /// A member was removed by an admin.
+    Remove { member_id: String },
+    /// The sending member left the group.
+    Leave { member_id: String },
+}
+
+/// Group control payload exchanged over pairwise sessions.
+///
+/// Carries a membership change, a sender key, or both, so a member joining or a
+/// key rotation after a removal only costs one pairwise message per recipient.
+///
+/// # Fields
+/// - `group_id`: Identifier of the group concerned.
+/// - `change`: Membership change, if any.
+/// - `sender_key`: The sender's current sender key, if it is being (re)distributed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupControlMessage {
+    pub group_id: String,
+    pub change: Option<GroupChange>,
+    pub sender_key: Option<SenderKeyDistributionMessage>,
+}
+
+impl GroupControlMessage {
+    /// Encodes the message as a payload-type byte followed by its JSON serialization.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![GROUP_CONTROL_PAYLOAD];
+        serde_json::to_writer(&mut bytes, self).expect("group control serialization failed");
+        bytes
+    }
+
+    /// Decodes a payload produced by [`GroupControlMessage::encode`].
+    ///
+    /// # Returns
+    /// The decoded message, or `None` if the payload is of another type or malformed.
+    pub(crate) fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes.split_first()? {
+            (&GROUP_CONTROL_PAYLOAD, json) => serde_json::from_slice(json).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of processing a [`GroupControlMessage`] received from another member.
+///
+/// # Fields
+/// - `group_id`: Identifier of the group concerned.
+/// - `change`: The membership change that was applied, if any.
+/// - `replies`: Pairwise messages to deliver, as `(recipient_id, message)` pairs
+///   (e.g. our own sender key after joining or after a rotation).
+#[derive(Debug, Clone)]
+pub struct GroupControlResult {
+    pub group_id: String,
+    pub change: Option<GroupChange>,
+    pub replies: Vec<(String, EncryptedMessage)>,
+}
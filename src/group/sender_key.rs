@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::{CryptoRng, OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypto_utils::encryption::{decrypt_chacha20, encrypt_chacha20},
+    keys::{
+        chain_key::ChainKey,
+        message_key::MessageKey,
+        sender_key_message::{SenderKeyDistributionMessage, SenderKeyMessage},
+    },
+};
+
+/// Maximum number of message keys a receiver will derive ahead of its chain
+/// to cope with out-of-order group messages.
+pub(crate) const MAX_SKIPPED_SENDER_KEYS: u32 = 2000;
+
+/// Maximum number of skipped message keys stored per sender key; the keys of the oldest
+/// iterations are evicted first.
+pub(crate) const MAX_STORED_SENDER_KEYS: usize = 2000;
+
+/// Our own sending state for one group.
+///
+/// Every group message is encrypted once with the next key of `chain` and signed
+/// with `signing_private`, so all members holding the matching
+/// [`SenderKeyDistributionMessage`] can decrypt and authenticate it.
+///
+/// # Fields
+/// - `key_id`: Random identifier of this sender key.
+/// - `chain`: Symmetric sender chain, advanced once per message.
+/// - `signing_private`: Ed25519 seed used to sign group messages.
+/// - `signing_public`: Corresponding Ed25519 public key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SenderKeyState {
+    key_id: u32,
+    chain: ChainKey,
+    signing_private: [u8; 32],
+    signing_public: [u8; 32],
+}
+
+impl SenderKeyState {
+    /// Generates a fresh sender key with a random chain key and signing key pair.
+    pub(crate) fn new() -> Self {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    /// Generates a fresh sender key from the given RNG.
+    ///
+    /// # Arguments
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible keys.
+    pub(crate) fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        let mut chain_key = [0u8; 32];
+        rng.fill_bytes(&mut chain_key);
+
+        let mut signing_private = [0u8; 32];
+        rng.fill_bytes(&mut signing_private);
+        let signing_public = SigningKey::from_bytes(&signing_private)
+            .verifying_key()
+            .to_bytes();
+
+        Self {
+            key_id: rng.next_u32(),
+            chain: ChainKey::new(chain_key, 0),
+            signing_private,
+            signing_public,
+        }
+    }
+
+    /// Builds the distribution message handing the current chain position to another member.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the group this key is used for.
+    pub(crate) fn distribution_message(&self, group_id: &str) -> SenderKeyDistributionMessage {
+        SenderKeyDistributionMessage {
+            group_id: group_id.to_string(),
+            key_id: self.key_id,
+            iteration: self.chain.get_index(),
+            chain_key: *self.chain.get_key(),
+            signing_key: self.signing_public,
+        }
+    }
+
+    /// Encrypts and signs a group message with the next key of the sender chain.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the group.
+    /// - `sender`: Sender name/ID.
+    /// - `plaintext`: Message to encrypt.
+    ///
+    /// # Returns
+    /// A signed [`SenderKeyMessage`].
+    pub(crate) fn encrypt(
+        &mut self,
+        group_id: &str,
+        sender: String,
+        plaintext: &[u8],
+    ) -> SenderKeyMessage {
+        let (next_ck, message_key) = self.chain.derive_next();
+        self.chain = next_ck;
+
+        let (ciphertext, nonce) = encrypt_chacha20(message_key.get_key(), plaintext);
+
+        let mut msg = SenderKeyMessage {
+            group_id: group_id.to_string(),
+            sender,
+            key_id: self.key_id,
+            iteration: message_key.get_index(),
+            nonce,
+            ciphertext,
+            signature: Vec::new(),
+        };
+
+        let signing_key = SigningKey::from_bytes(&self.signing_private);
+        msg.signature = signing_key.sign(&msg.signed_bytes()).to_vec();
+        msg
+    }
+}
+
+/// Receiving state for another member's sender key in one group.
+///
+/// # Fields
+/// - `key_id`: Identifier of the sender key this record tracks.
+/// - `chain`: Next expected position in the sender chain.
+/// - `signing_public`: Ed25519 key used to verify the sender's messages.
+/// - `skipped_message_keys`: Keys derived for messages that have not arrived yet, by
+///   iteration, at most [`MAX_STORED_SENDER_KEYS`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SenderKeyRecord {
+    key_id: u32,
+    chain: ChainKey,
+    signing_public: [u8; 32],
+    skipped_message_keys: BTreeMap<u32, MessageKey>,
+}
+
+impl SenderKeyRecord {
+    /// Creates a receiving record from a member's distribution message.
+    pub(crate) fn from_distribution(skdm: &SenderKeyDistributionMessage) -> Self {
+        Self {
+            key_id: skdm.key_id,
+            chain: ChainKey::new(skdm.chain_key, skdm.iteration),
+            signing_public: skdm.signing_key,
+            skipped_message_keys: BTreeMap::new(),
+        }
+    }
+
+    /// Verifies and decrypts a group message from the member this record belongs to.
+    ///
+    /// The signature is checked before the chain is advanced, so forged messages
+    /// never consume or skip message keys.
+    ///
+    /// # Returns
+    /// - `Some(plaintext)` if the message is authentic and decrypts
+    /// - `None` if the key id is unknown, the signature is invalid, the message key
+    ///   was already consumed, or decryption fails
+    pub(crate) fn decrypt(&mut self, msg: &SenderKeyMessage) -> Option<Vec<u8>> {
+        if msg.key_id != self.key_id {
+            return None;
+        }
+
+        let verifying_key = VerifyingKey::from_bytes(&self.signing_public).ok()?;
+        let signature = Signature::from_slice(&msg.signature).ok()?;
+        verifying_key.verify(&msg.signed_bytes(), &signature).ok()?;
+
+        if msg.iteration < self.chain.get_index() {
+            let message_key = self.skipped_message_keys.remove(&msg.iteration)?;
+            return decrypt_chacha20(message_key.get_key(), &msg.nonce, &msg.ciphertext).ok();
+        }
+
+        if msg.iteration - self.chain.get_index() > MAX_SKIPPED_SENDER_KEYS {
+            return None;
+        }
+
+        while self.chain.get_index() < msg.iteration {
+            let (next_ck, skipped_key) = self.chain.derive_next();
+            self.skipped_message_keys
+                .insert(skipped_key.get_index(), skipped_key);
+            if self.skipped_message_keys.len() > MAX_STORED_SENDER_KEYS {
+                self.skipped_message_keys.pop_first();
+            }
+            self.chain = next_ck;
+        }
+
+        let (next_ck, message_key) = self.chain.derive_next();
+        self.chain = next_ck;
+
+        decrypt_chacha20(message_key.get_key(), &msg.nonce, &msg.ciphertext).ok()
+    }
+}
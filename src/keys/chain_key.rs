@@ -1,3 +1,4 @@
+use crate::crypto_utils::signal_kdf::{self, KdfMode};
 use crate::keys::message_key::MessageKey;
 use hkdf::hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
@@ -27,6 +28,25 @@ impl ChainKey {
     /// # Returns
     /// A tuple `(next_chain_key, message_key)`
     pub(crate) fn derive_next(&self) -> (ChainKey, MessageKey) {
+        self.derive_next_with(KdfMode::Default)
+    }
+
+    /// Derives the next `ChainKey` and `MessageKey` with the constants of `mode`.
+    ///
+    /// In [`KdfMode::Signal`], the message key is HMAC(chain_key, 0x01) and the next chain
+    /// key HMAC(chain_key, 0x02), as in libsignal.
+    ///
+    /// # Returns
+    /// A tuple `(next_chain_key, message_key)`
+    pub(crate) fn derive_next_with(&self, mode: KdfMode) -> (ChainKey, MessageKey) {
+        if mode == KdfMode::Signal {
+            let (next_ck, message_key) = signal_kdf::chain_step(&self.key);
+            return (
+                ChainKey::new(next_ck, self.index + 1),
+                MessageKey::new(message_key, self.index),
+            );
+        }
+
         let mut hmac = Hmac::<Sha256>::new_from_slice(&self.key).unwrap();
 
         // 🔑 Derive message key with fixed context string
@@ -66,4 +86,14 @@ impl ChainKey {
     pub(crate) fn get_index(&self) -> u32 {
         self.index
     }
+
+    /// Returns the raw chain key bytes.
+    ///
+    /// # Returns
+    /// A reference to the 32-byte chain key.
+    ///
+    /// > ⚠️ This function exposes sensitive material. Handle with care.
+    pub(crate) fn get_key(&self) -> &[u8; 32] {
+        &self.key
+    }
 }
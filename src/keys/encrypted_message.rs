@@ -1,6 +1,82 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
+use crate::double_ratchet::config::SessionConfig;
+
+/// An encrypted message header (ratchet public key, message index and previous chain
+/// length).
+///
+/// # Fields
+/// - `nonce`: AEAD nonce (its size depends on the session's cipher suite).
+/// - `ciphertext`: The encrypted header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedHeader {
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+impl EncryptedHeader {
+    /// Serializes the header as associated data for the message body.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        [self.nonce.as_slice(), self.ciphertext.as_slice()].concat()
+    }
+}
+
+/// Kind of key material carried by a [`PqChunk`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PqChunkKind {
+    /// Part of an ML-KEM encapsulation key.
+    EncapsulationKey,
+    /// Part of an ML-KEM ciphertext.
+    Ciphertext,
+}
+
+/// A fragment of post-quantum ratchet key material.
+///
+/// # Fields
+/// - `epoch`: PQ epoch being negotiated.
+/// - `kind`: Whether the fragment belongs to an encapsulation key or a ciphertext.
+/// - `index`: Position of the fragment.
+/// - `total`: Number of fragments of the full key material.
+/// - `data`: Fragment bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PqChunk {
+    pub epoch: u64,
+    pub kind: PqChunkKind,
+    pub index: u16,
+    pub total: u16,
+    pub data: Vec<u8>,
+}
+
+/// Post-quantum ratchet data attached to a message.
+///
+/// # Fields
+/// - `epoch`: PQ epoch whose key was mixed into the message key.
+/// - `chunk`: Key material fragment for the epoch being negotiated, if any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PqRatchetHeader {
+    pub epoch: u64,
+    pub chunk: Option<PqChunk>,
+}
+
+impl PqRatchetHeader {
+    /// Serializes the PQ header as associated data for the message body.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.epoch.to_be_bytes().to_vec();
+        if let Some(chunk) = &self.chunk {
+            bytes.extend_from_slice(&chunk.epoch.to_be_bytes());
+            bytes.push(match chunk.kind {
+                PqChunkKind::EncapsulationKey => 0,
+                PqChunkKind::Ciphertext => 1,
+            });
+            bytes.extend_from_slice(&chunk.index.to_be_bytes());
+            bytes.extend_from_slice(&chunk.total.to_be_bytes());
+            bytes.extend_from_slice(&chunk.data);
+        }
+        bytes
+    }
+}
+
 /// Represents a ratcheted, AEAD-encrypted message exchanged between users.
 ///
 /// This structure is designed to hold all necessary metadata for decryption
@@ -9,36 +85,51 @@ use std::fmt::Display;
 /// # Fields
 /// - `sender`: Sender's identity (used for display/logging).
 /// - `receiver`: Receiver's identity (used for routing).
-/// - `nonce`: A 12-byte nonce for AEAD encryption.
-/// - `ciphertext`: The encrypted payload.
-/// - `ratchet_pub`: Sender's public ratchet key used for DH ratchet.
-/// - `message_index`: Index within the sender's message chain.
+/// - `ciphertext`: The encrypted payload. Its AEAD nonce is derived from the message key,
+///   so none is transmitted.
+/// - `ratchet_pub`: Sender's public ratchet key used for DH ratchet (zeroed when the
+///   header is encrypted).
+/// - `message_index`: Index within the sender's message chain (zeroed when the header is
+///   encrypted).
+/// - `previous_counter`: Number of messages in the sender's previous sending chain, so the
+///   receiver can store the keys of those it has not received yet (zeroed when the header
+///   is encrypted).
+/// - `header`: Encrypted header, present when the session uses header encryption.
+/// - `pq`: Post-quantum ratchet data, present when the session uses the PQ ratchet.
 /// - `opk_used`: One-time pre-key (if any) used to establish the session.
 /// - `ek_used`: Ephemeral key used during session negotiation (if applicable).
+/// - `kem_prekey_id`: ID of the KEM pre-key encapsulated to (PQXDH prekey messages only).
+/// - `kem_ciphertext`: ML-KEM-768 ciphertext (PQXDH prekey messages only).
+/// - `session_config`: Session options chosen by the initiator (prekey messages only),
+///   authenticated with the message body.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedMessage {
     pub sender: String,
     pub receiver: String,
-    pub nonce: [u8; 12],
     pub ciphertext: Vec<u8>,
     pub ratchet_pub: [u8; 32], // DH public key used in ratchet step
     pub message_index: u32,    // Index in chain key (CKs.index)
+    pub previous_counter: u32, // Length of the previous sending chain (PN)
+    pub header: Option<EncryptedHeader>,
+    pub pq: Option<PqRatchetHeader>,
     pub opk_used: Option<[u8; 32]>,
     pub ek_used: Option<[u8; 32]>,
+    pub kem_prekey_id: Option<String>,
+    pub kem_ciphertext: Option<Vec<u8>>,
+    pub session_config: Option<SessionConfig>,
 }
 
 impl Display for EncryptedMessage {
     /// Formats the `EncryptedMessage` for human-readable display.
     ///
-    /// Shows sender/receiver, nonce, ciphertext, ratchet public key, and message index
+    /// Shows sender/receiver, ciphertext, ratchet public key, and message index
     /// as hex-encoded values for clarity.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "EncryptedMessage {{ sender: {}, receiver: {}, nonce: {}, ciphertext: {}, ratchet_pub: {}, message_index: {} }}",
+            "EncryptedMessage {{ sender: {}, receiver: {}, ciphertext: {}, ratchet_pub: {}, message_index: {} }}",
             self.sender,
             self.receiver,
-            hex::encode(self.nonce),
             hex::encode(&self.ciphertext),
             hex::encode(self.ratchet_pub),
             self.message_index
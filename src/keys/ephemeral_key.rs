@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use rand_core::OsRng;
+use rand_core::{CryptoRng, OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
 
@@ -25,7 +25,18 @@ impl EphemeralKey {
     /// # Returns
     /// A new `EphemeralKey` with an X25519 key pair.
     pub fn new() -> Self {
-        let private_key = StaticSecret::random_from_rng(&mut OsRng);
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    /// Generates a fresh `EphemeralKey` from the given RNG.
+    ///
+    /// # Arguments
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible keys.
+    ///
+    /// # Returns
+    /// A new `EphemeralKey` with an X25519 key pair.
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        let private_key = StaticSecret::random_from_rng(rng);
         let public_key = X25519PublicKey::from(&private_key);
 
         Self {
@@ -45,6 +56,12 @@ impl EphemeralKey {
     }
 }
 
+impl Default for EphemeralKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display for EphemeralKey {
     /// Displays both public and private keys in hexadecimal format.
     /// Intended for debugging or inspection (not recommended in production logs).
@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 use ed25519_dalek::SigningKey;
-use rand_core::{OsRng, RngCore};
+use rand_core::{CryptoRng, OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
 
@@ -28,11 +28,22 @@ impl IdentityKey {
     /// # Returns
     /// A new `IdentityKey` instance containing both X25519 and Ed25519 key pairs.
     pub fn new() -> Self {
-        let dh_private = StaticSecret::random_from_rng(&mut OsRng);
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    /// Generates a fresh `IdentityKey` from the given RNG.
+    ///
+    /// # Arguments
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible keys.
+    ///
+    /// # Returns
+    /// A new `IdentityKey` instance containing both X25519 and Ed25519 key pairs.
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        let dh_private = StaticSecret::random_from_rng(&mut *rng);
         let dh_public = X25519PublicKey::from(&dh_private);
 
         let mut signing_bytes = [0u8; 32];
-        OsRng.fill_bytes(&mut signing_bytes);
+        rng.fill_bytes(&mut signing_bytes);
         let sign_private = SigningKey::from_bytes(&signing_bytes);
         let sign_public = sign_private.verifying_key();
 
@@ -61,6 +72,17 @@ impl IdentityKey {
     pub fn signing_key(&self) -> SigningKey {
         SigningKey::from_bytes(&self.sign_private)
     }
+
+    /// Returns the Ed25519 public key used to verify signatures made by this identity.
+    pub fn signing_public(&self) -> [u8; 32] {
+        self.sign_public
+    }
+}
+
+impl Default for IdentityKey {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Display for IdentityKey {
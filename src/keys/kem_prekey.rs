@@ -0,0 +1,122 @@
+use std::fmt::Display;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto_utils::{
+    kem::{kem_decapsulate, kem_encapsulate, kem_keypair},
+    rng::random_uuid,
+};
+
+/// A signed ML-KEM-768 pre-key used by the PQXDH handshake.
+///
+/// Bundles publish one last-resort KEM pre-key, used whenever no one-time KEM pre-key is
+/// left, and a pool of one-time KEM pre-keys.
+///
+/// # Fields
+/// - `id`: A UUID string uniquely identifying this pre-key.
+/// - `private`: Encoded ML-KEM-768 decapsulation key. Not to be shared.
+/// - `public`: Encoded ML-KEM-768 encapsulation key.
+/// - `signature`: Ed25519 signature of `public` by the owner's identity key.
+/// - `last_resort`: Whether this is the owner's last-resort KEM pre-key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KemPreKey {
+    pub id: String,
+    private: Vec<u8>,
+    pub public: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub last_resort: bool,
+}
+
+impl KemPreKey {
+    /// Generates a KEM pre-key signed by the given identity key.
+    ///
+    /// # Arguments
+    /// - `identity_signing_key`: Long-term Ed25519 identity key of the owner.
+    /// - `last_resort`: Whether the key is the owner's last-resort KEM pre-key.
+    /// - `rng`: Source of randomness for the key pair and its ID.
+    pub(crate) fn new<R: CryptoRng + RngCore>(
+        identity_signing_key: &SigningKey,
+        last_resort: bool,
+        rng: &mut R,
+    ) -> Self {
+        let (private, public) = kem_keypair(rng);
+        let signature = identity_signing_key.sign(&public);
+
+        Self {
+            id: random_uuid(rng),
+            private,
+            public,
+            signature: signature.to_vec(),
+            last_resort,
+        }
+    }
+
+    /// Returns the public part of this pre-key, for publication in a bundle.
+    pub(crate) fn public_key(&self) -> KemPreKeyPublic {
+        KemPreKeyPublic {
+            id: self.id.clone(),
+            public: self.public.clone(),
+            signature: self.signature.clone(),
+            last_resort: self.last_resort,
+        }
+    }
+
+    /// Recovers the shared secret encapsulated to this pre-key.
+    ///
+    /// # Returns
+    /// The 32-byte KEM shared secret, or `None` if the ciphertext is malformed.
+    pub(crate) fn decapsulate(&self, ciphertext: &[u8]) -> Option<[u8; 32]> {
+        kem_decapsulate(&self.private, ciphertext)
+    }
+}
+
+impl Display for KemPreKey {
+    /// Displays the ID and a fingerprint of the public key.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "id: {}, public: {}..., last_resort: {}",
+            self.id,
+            hex::encode(&self.public[..16]),
+            self.last_resort
+        )
+    }
+}
+
+/// A public-only KEM pre-key, as published in a user's bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KemPreKeyPublic {
+    pub id: String,
+    pub public: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub last_resort: bool,
+}
+
+impl KemPreKeyPublic {
+    /// Checks the pre-key signature against the owner's Ed25519 identity key.
+    pub fn verify(&self, identity_signing_public: &[u8; 32]) -> bool {
+        let Ok(verifying_key) = VerifyingKey::from_bytes(identity_signing_public) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(&self.signature) else {
+            return false;
+        };
+        verifying_key.verify(&self.public, &signature).is_ok()
+    }
+
+    /// Encapsulates a fresh shared secret to this pre-key.
+    ///
+    /// # Arguments
+    /// - `rng`: Source of randomness.
+    ///
+    /// # Returns
+    /// `(ciphertext, shared_secret)`, or `None` if the key is malformed.
+    pub(crate) fn encapsulate<R: CryptoRng + RngCore>(
+        &self,
+        rng: &mut R,
+    ) -> Option<(Vec<u8>, [u8; 32])> {
+        kem_encapsulate(&self.public, rng)
+    }
+}
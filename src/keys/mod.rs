@@ -2,9 +2,13 @@ pub mod chain_key;
 pub mod encrypted_message;
 pub mod ephemeral_key;
 pub mod identity;
+pub mod kem_prekey;
 pub mod message_key;
 pub mod one_time_prekey;
 pub mod ratchet_key;
+pub mod retry_request;
 pub mod root_key;
+pub mod sealed_sender_message;
+pub mod sender_key_message;
 pub mod session_key;
 pub mod signed_prekey;
@@ -1,9 +1,11 @@
 use std::fmt::Display;
 
-use rand_core::OsRng;
+use rand_core::{CryptoRng, OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
 
+use crate::crypto_utils::rng::random_uuid;
+
 /// A single one-time pre-key (OTPK) containing an X25519 key pair and a unique ID.
 ///
 /// This key is intended to be used exactly once during X3DH session establishment.
@@ -26,10 +28,21 @@ impl OneTimePreKey {
     /// # Returns
     /// A `OneTimePreKey` with fresh ID and X25519 key pair.
     pub fn new() -> Self {
-        let private_key = StaticSecret::random_from_rng(&mut OsRng);
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    /// Generates a new one-time pre-key from the given RNG.
+    ///
+    /// # Arguments
+    /// - `rng`: Source of randomness for the key pair and its ID.
+    ///
+    /// # Returns
+    /// A `OneTimePreKey` with fresh ID and X25519 key pair.
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        let private_key = StaticSecret::random_from_rng(&mut *rng);
         let public_key = X25519PublicKey::from(&private_key);
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: random_uuid(rng),
             private: private_key.to_bytes(),
             public: *public_key.as_bytes(),
         }
@@ -44,6 +57,12 @@ impl OneTimePreKey {
     }
 }
 
+impl Default for OneTimePreKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display for OneTimePreKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -69,11 +88,14 @@ impl OneTimePreKeyGroup {
     ///
     /// # Arguments
     /// - `size`: The number of pre-keys to generate.
+    /// - `rng`: Source of randomness.
     ///
     /// # Returns
     /// A `OneTimePreKeyGroup` containing `size` freshly generated keys.
-    pub(crate) fn new(size: usize) -> Self {
-        let keys = (0..size).map(|_| OneTimePreKey::new()).collect();
+    pub(crate) fn new<R: CryptoRng + RngCore>(size: usize, rng: &mut R) -> Self {
+        let keys = (0..size)
+            .map(|_| OneTimePreKey::new_with_rng(rng))
+            .collect();
         Self { keys }
     }
 
@@ -88,6 +110,14 @@ impl OneTimePreKeyGroup {
         self.keys.iter().find(|k| k.public == pubkey).cloned()
     }
 
+    /// Deletes a used one-time pre-key so that it cannot establish another session.
+    ///
+    /// # Arguments
+    /// - `pubkey`: The 32-byte public key of the used key.
+    pub(crate) fn remove(&mut self, pubkey: [u8; 32]) {
+        self.keys.retain(|k| k.public != pubkey);
+    }
+
     /// Returns the number of available keys in the group.
     pub(crate) fn len(&self) -> usize {
         self.keys.len()
@@ -140,7 +170,9 @@ impl OneTimePreKeyGroupPublic {
     /// Selects a usable one-time pre-key from the group (non-consuming).
     ///
     /// This method simulates a one-time selection from the server side.
-    /// In a production system, the selected key would be deleted server-side.
+    /// In a production system, the selected key would be deleted server-side; here the
+    /// owner deletes it once a session established with it decrypts its first message, so
+    /// bundles fetched before that point go stale.
     ///
     /// # Returns
     /// An available `OneTimePreKeyPublic`, or `None` if the group is empty.
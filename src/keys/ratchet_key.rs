@@ -1,4 +1,4 @@
-use rand_core::OsRng;
+use rand_core::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
 use x25519_dalek::{PublicKey, StaticSecret};
 
@@ -17,14 +17,15 @@ pub(crate) struct RatchetKey {
 }
 
 impl RatchetKey {
-    /// Generates a new random `RatchetKey` using secure RNG.
+    /// Generates a new random `RatchetKey` from the given RNG.
     ///
-    /// Internally uses `OsRng` to produce a cryptographically secure X25519 key pair.
+    /// # Arguments
+    /// - `rng`: Cryptographically secure RNG. A seeded RNG yields reproducible keys.
     ///
     /// # Returns
     /// A newly generated `RatchetKey` instance.
-    pub(crate) fn new() -> Self {
-        let private = StaticSecret::random_from_rng(&mut OsRng);
+    pub(crate) fn new<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        let private = StaticSecret::random_from_rng(rng);
         let public = PublicKey::from(&private);
 
         Self {
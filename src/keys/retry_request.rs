@@ -0,0 +1,92 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+
+/// How long after it was issued a [`RetryRequest`] is still answered.
+pub(crate) const MAX_RETRY_REQUEST_AGE: TimeDelta = TimeDelta::days(1);
+
+/// How far in the future a [`RetryRequest`] may be dated, to tolerate clock skew.
+pub(crate) const MAX_RETRY_REQUEST_SKEW: TimeDelta = TimeDelta::minutes(5);
+
+/// Asks the sender of a message that could not be decrypted to send it again.
+///
+/// The request is not encrypted, since the session it would travel over may be the one
+/// that is broken, but it is signed with the requester's Ed25519 identity key. The failed
+/// message is referenced by the hash of its ciphertext, which both parties can compute.
+///
+/// # Fields
+/// - `requester_id`: User ID of the receiver that failed to decrypt the message.
+/// - `sender_id`: User ID of the sender of the failed message.
+/// - `message_hash`: SHA-256 of the failed message's ciphertext.
+/// - `timestamp`: When the request was issued.
+/// - `signature`: Ed25519 signature over all the fields above.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryRequest {
+    pub requester_id: String,
+    pub sender_id: String,
+    pub message_hash: [u8; 32],
+    pub timestamp: DateTime<Utc>,
+    pub signature: Vec<u8>,
+}
+
+impl RetryRequest {
+    /// Returns the byte string covered by the requester's signature.
+    ///
+    /// # Returns
+    /// `requester_id || sender_id || message_hash || timestamp`, with strings prefixed by
+    /// their big-endian `u32` length and the timestamp in big-endian milliseconds.
+    pub(crate) fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.requester_id.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.requester_id.as_bytes());
+        bytes.extend_from_slice(&(self.sender_id.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.sender_id.as_bytes());
+        bytes.extend_from_slice(&self.message_hash);
+        bytes.extend_from_slice(&self.timestamp.timestamp_millis().to_be_bytes());
+        bytes
+    }
+
+    /// Checks that the request was issued within [`MAX_RETRY_REQUEST_AGE`] of `now`, and
+    /// is not dated more than [`MAX_RETRY_REQUEST_SKEW`] in the future.
+    pub(crate) fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        let age = now - self.timestamp;
+        age <= MAX_RETRY_REQUEST_AGE && age >= -MAX_RETRY_REQUEST_SKEW
+    }
+
+    /// Checks the request signature against the requester's Ed25519 identity key.
+    pub fn verify(&self, identity_signing_public: &[u8; 32]) -> bool {
+        let Ok(verifying_key) = VerifyingKey::from_bytes(identity_signing_public) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(&self.signature) else {
+            return false;
+        };
+        verifying_key
+            .verify(&self.signed_bytes(), &signature)
+            .is_ok()
+    }
+}
+
+/// Computes the hash referencing a message in a [`RetryRequest`].
+///
+/// # Returns
+/// SHA-256 of `ciphertext`.
+pub(crate) fn message_hash(ciphertext: &[u8]) -> [u8; 32] {
+    Sha256::digest(ciphertext).into()
+}
+
+impl Display for RetryRequest {
+    /// Formats the `RetryRequest` for human-readable display, hex-encoding the hash.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RetryRequest {{ requester_id: {}, sender_id: {}, message_hash: {}, timestamp: {} }}",
+            self.requester_id,
+            self.sender_id,
+            hex::encode(self.message_hash),
+            self.timestamp
+        )
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::crypto_utils::seal::SealedBox;
+
+/// Represents an [`EncryptedMessage`](crate::keys::encrypted_message::EncryptedMessage)
+/// wrapped so that the relay only learns who it is for, not who sent it.
+///
+/// # Fields
+/// - `receiver`: Recipient's user ID, the only routing metadata left in clear.
+/// - `static_box`: Sender's identity public key, sealed to the recipient's identity key
+///   with an ephemeral key.
+/// - `nonce`: A 12-byte nonce for AEAD encryption of `ciphertext`.
+/// - `ciphertext`: Sender identity and inner message, encrypted under a key derived from
+///   both identity keys and bound to `static_box`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedSenderMessage {
+    pub receiver: String,
+    pub static_box: SealedBox,
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+}
+
+impl Display for SealedSenderMessage {
+    /// Formats the `SealedSenderMessage` for human-readable display, hex-encoding binary fields.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SealedSenderMessage {{ receiver: {}, ephemeral_public: {}, nonce: {}, ciphertext: {} }}",
+            self.receiver,
+            hex::encode(self.static_box.ephemeral_public),
+            hex::encode(self.nonce),
+            hex::encode(&self.ciphertext)
+        )
+    }
+}
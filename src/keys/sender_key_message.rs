@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Carries a member's sender chain key to another group member.
+///
+/// This message is never sent in the clear: it is serialized and encrypted over the
+/// existing pairwise Double Ratchet session between the two members.
+///
+/// # Fields
+/// - `group_id`: Identifier of the group this sender key belongs to.
+/// - `key_id`: Random identifier of the sender key (changes on every rotation).
+/// - `iteration`: Chain index at which `chain_key` starts.
+/// - `chain_key`: 32-byte sender chain key.
+/// - `signing_key`: Ed25519 public key used to verify the sender's group messages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SenderKeyDistributionMessage {
+    pub group_id: String,
+    pub key_id: u32,
+    pub iteration: u32,
+    pub chain_key: [u8; 32],
+    pub signing_key: [u8; 32],
+}
+
+/// Represents a group message encrypted once under the sender's symmetric chain.
+///
+/// # Fields
+/// - `group_id`: Identifier of the group.
+/// - `sender`: Sender's identity (used for display/logging).
+/// - `key_id`: Identifier of the sender key used for encryption.
+/// - `iteration`: Index of the message key within the sender chain.
+/// - `nonce`: A 12-byte nonce for AEAD encryption.
+/// - `ciphertext`: The encrypted payload.
+/// - `signature`: Ed25519 signature over all the fields above except `sender`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SenderKeyMessage {
+    pub group_id: String,
+    pub sender: String,
+    pub key_id: u32,
+    pub iteration: u32,
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SenderKeyMessage {
+    /// Returns the byte string covered by the sender's signature.
+    ///
+    /// # Returns
+    /// `group_id || key_id || iteration || nonce || ciphertext`, with integers in big-endian
+    /// and `group_id` prefixed by its big-endian `u32` length.
+    pub(crate) fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.group_id.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.group_id.as_bytes());
+        bytes.extend_from_slice(&self.key_id.to_be_bytes());
+        bytes.extend_from_slice(&self.iteration.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+}
+
+impl Display for SenderKeyMessage {
+    /// Formats the `SenderKeyMessage` for human-readable display, hex-encoding binary fields.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SenderKeyMessage {{ group_id: {}, sender: {}, key_id: {}, iteration: {}, nonce: {}, ciphertext: {} }}",
+            self.group_id,
+            self.sender,
+            self.key_id,
+            self.iteration,
+            hex::encode(self.nonce),
+            hex::encode(&self.ciphertext)
+        )
+    }
+}
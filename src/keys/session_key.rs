@@ -6,6 +6,9 @@ use serde::{Deserialize, Serialize};
 /// - `bytes`: A 32-byte fixed-length array that holds the raw session key material.
 /// - `sender`: The identifier (e.g., username, node ID) of the entity who initiated the session.
 /// - `receiver`: The identifier of the intended recipient of the session.
+/// - `chain_key`: Initial chain key, for KDFs that derive it alongside the root key
+///   (Signal [`KdfMode`](crate::crypto_utils::signal_kdf::KdfMode)). In that case `bytes`
+///   is the root key itself.
 ///
 /// # Serialization
 /// This struct implements `Serialize` and `Deserialize` from Serde, making it suitable for
@@ -16,6 +19,7 @@ pub(crate) struct SessionKey {
     bytes: [u8; 32],
     pub sender: String,
     pub receiver: String,
+    chain_key: Option<[u8; 32]>,
 }
 
 impl SessionKey {
@@ -33,9 +37,22 @@ impl SessionKey {
             bytes,
             sender,
             receiver,
+            chain_key: None,
         }
     }
 
+    /// Attaches the initial chain key derived alongside the session key.
+    ///
+    /// # Arguments
+    /// - `chain_key`: The 32-byte initial chain key.
+    ///
+    /// # Returns
+    /// The `SessionKey` carrying `chain_key`.
+    pub(crate) fn with_chain_key(mut self, chain_key: [u8; 32]) -> Self {
+        self.chain_key = Some(chain_key);
+        self
+    }
+
     /// Returns a reference to the raw 32-byte key material.
     ///
     /// # Returns
@@ -43,4 +60,11 @@ impl SessionKey {
     pub(crate) fn get_bytes(&self) -> &[u8; 32] {
         &self.bytes
     }
+
+    /// Returns the initial chain key, if the KDF derived one.
+    ///
+    /// > ⚠️ This function exposes sensitive material. Handle with care.
+    pub(crate) fn get_chain_key(&self) -> Option<&[u8; 32]> {
+        self.chain_key.as_ref()
+    }
 }
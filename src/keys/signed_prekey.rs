@@ -2,9 +2,11 @@ use std::fmt::Display;
 
 use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signer, SigningKey};
-use rand_core::OsRng;
+use rand_core::{CryptoRng, RngCore};
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
 
+use crate::crypto_utils::rng::random_uuid;
+
 /// Represents a signed X25519 pre-key used in ephemeral key exchange protocols.
 ///
 /// # Fields
@@ -27,17 +29,21 @@ impl SignedPreKey {
     ///
     /// # Arguments
     /// - `identity_signing_key`: Reference to a long-term Ed25519 identity key that will sign the generated public key.
+    /// - `rng`: Source of randomness for the key pair and its ID.
     ///
     /// # Returns
     /// A fully initialized `SignedPreKey` with a fresh X25519 key pair, signed public key, and timestamp.
-    pub(crate) fn new(identity_signing_key: &SigningKey) -> Self {
-        let private_key = StaticSecret::random_from_rng(&mut OsRng);
+    pub(crate) fn new<R: CryptoRng + RngCore>(
+        identity_signing_key: &SigningKey,
+        rng: &mut R,
+    ) -> Self {
+        let private_key = StaticSecret::random_from_rng(&mut *rng);
         let public_key = X25519PublicKey::from(&private_key);
 
         let signature = identity_signing_key.sign(public_key.as_bytes());
 
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: random_uuid(rng),
             private: private_key.to_bytes(),
             public: *public_key.as_bytes(),
             signature: signature.to_vec(),
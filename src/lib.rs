@@ -1,6 +1,11 @@
+pub mod content;
 pub mod crypto_utils;
 pub mod double_ratchet;
+pub mod error;
+pub mod group;
 pub mod keys;
+pub mod sealed_sender;
+pub mod treekem;
 pub mod user;
 pub mod x3dh;
 
@@ -0,0 +1,221 @@
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::{CryptoRng, OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Checks an Ed25519 signature over `bytes`, returning `false` on malformed input.
+fn verify_signature(public: &[u8; 32], bytes: &[u8], signature: &[u8]) -> bool {
+    let Ok(verifying_key) = VerifyingKey::from_bytes(public) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+    verifying_key.verify(bytes, &signature).is_ok()
+}
+
+/// Generates a random Ed25519 seed and its public key from the given RNG.
+fn generate_signing_key<R: CryptoRng + RngCore>(rng: &mut R) -> ([u8; 32], [u8; 32]) {
+    let mut private = [0u8; 32];
+    rng.fill_bytes(&mut private);
+    let public = SigningKey::from_bytes(&private).verifying_key().to_bytes();
+    (private, public)
+}
+
+/// The service's long-term trust anchor.
+///
+/// Clients only need [`TrustRoot::public_key`]; the private half signs the
+/// [`ServerCertificate`]s of the keys that issue sender certificates. A root can be
+/// generated locally for tests and development deployments.
+///
+/// # Fields
+/// - `signing_private`: Ed25519 seed of the root key.
+/// - `signing_public`: Corresponding Ed25519 public key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustRoot {
+    signing_private: [u8; 32],
+    signing_public: [u8; 32],
+}
+
+impl TrustRoot {
+    /// Generates a fresh trust root.
+    pub fn new() -> Self {
+        Self::new_with_rng(&mut OsRng)
+    }
+
+    /// Generates a trust root from the given RNG.
+    ///
+    /// # Arguments
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible keys.
+    pub fn new_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        let (signing_private, signing_public) = generate_signing_key(rng);
+        Self {
+            signing_private,
+            signing_public,
+        }
+    }
+
+    /// Returns the Ed25519 public key clients use to validate certificates.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing_public
+    }
+
+    /// Generates a new server signing key and certifies it under this root.
+    ///
+    /// # Arguments
+    /// - `key_id`: Identifier of the server key, allowing rotation.
+    pub fn issue_server(&self, key_id: u32) -> CertificateServer {
+        self.issue_server_with_rng(key_id, &mut OsRng)
+    }
+
+    /// Generates a new server signing key from the given RNG and certifies it under this
+    /// root.
+    ///
+    /// # Arguments
+    /// - `key_id`: Identifier of the server key, allowing rotation.
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible keys.
+    pub fn issue_server_with_rng<R: CryptoRng + RngCore>(
+        &self,
+        key_id: u32,
+        rng: &mut R,
+    ) -> CertificateServer {
+        let (signing_private, key) = generate_signing_key(rng);
+        let mut certificate = ServerCertificate {
+            key_id,
+            key,
+            signature: Vec::new(),
+        };
+        certificate.signature = SigningKey::from_bytes(&self.signing_private)
+            .sign(&certificate.signed_bytes())
+            .to_vec();
+
+        CertificateServer {
+            certificate,
+            signing_private,
+        }
+    }
+}
+
+impl Default for TrustRoot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A server signing key vouched for by the trust root.
+///
+/// # Fields
+/// - `key_id`: Identifier of the server key.
+/// - `key`: Ed25519 public key of the server.
+/// - `signature`: Trust root signature over `key_id || key`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerCertificate {
+    pub key_id: u32,
+    pub key: [u8; 32],
+    pub signature: Vec<u8>,
+}
+
+impl ServerCertificate {
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.key_id.to_be_bytes());
+        bytes.extend_from_slice(&self.key);
+        bytes
+    }
+
+    /// Returns `true` if the certificate is signed by the given trust root.
+    pub fn verify(&self, trust_root: &[u8; 32]) -> bool {
+        verify_signature(trust_root, &self.signed_bytes(), &self.signature)
+    }
+}
+
+/// Server-side issuer of sender certificates.
+///
+/// # Fields
+/// - `certificate`: The server key's certificate, embedded in every sender certificate.
+/// - `signing_private`: Ed25519 seed of the server key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateServer {
+    pub certificate: ServerCertificate,
+    signing_private: [u8; 32],
+}
+
+impl CertificateServer {
+    /// Issues a certificate binding a user and device to an identity key until `expires`.
+    ///
+    /// # Arguments
+    /// - `sender_id`: User ID of the certificate holder.
+    /// - `device_id`: Device of the certificate holder.
+    /// - `identity_key`: X25519 identity public key of the holder.
+    /// - `expires`: Instant after which the certificate is rejected.
+    pub fn issue_sender_certificate(
+        &self,
+        sender_id: &str,
+        device_id: u32,
+        identity_key: [u8; 32],
+        expires: DateTime<Utc>,
+    ) -> SenderCertificate {
+        let mut certificate = SenderCertificate {
+            sender_id: sender_id.to_string(),
+            device_id,
+            identity_key,
+            expires,
+            signer: self.certificate.clone(),
+            signature: Vec::new(),
+        };
+        certificate.signature = SigningKey::from_bytes(&self.signing_private)
+            .sign(&certificate.signed_bytes())
+            .to_vec();
+        certificate
+    }
+}
+
+/// Short-lived statement from the server that `identity_key` belongs to `sender_id`.
+///
+/// Carried inside sealed-sender messages so that recipients can check who sent them
+/// without the relay learning it.
+///
+/// # Fields
+/// - `sender_id`: User ID of the sender.
+/// - `device_id`: Device of the sender.
+/// - `identity_key`: X25519 identity public key of the sender.
+/// - `expires`: Expiry instant.
+/// - `signer`: Certificate of the server key that signed this certificate.
+/// - `signature`: Server signature over all the fields above except `signer`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SenderCertificate {
+    pub sender_id: String,
+    pub device_id: u32,
+    pub identity_key: [u8; 32],
+    pub expires: DateTime<Utc>,
+    pub signer: ServerCertificate,
+    pub signature: Vec<u8>,
+}
+
+impl SenderCertificate {
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.sender_id.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.sender_id.as_bytes());
+        bytes.extend_from_slice(&self.device_id.to_be_bytes());
+        bytes.extend_from_slice(&self.identity_key);
+        bytes.extend_from_slice(&self.expires.timestamp_millis().to_be_bytes());
+        bytes.extend_from_slice(&self.signer.key_id.to_be_bytes());
+        bytes
+    }
+
+    /// Validates the certificate chain and expiry.
+    ///
+    /// # Arguments
+    /// - `trust_root`: Ed25519 public key of the trust root.
+    /// - `now`: Current time.
+    ///
+    /// # Returns
+    /// `true` if the server certificate is signed by `trust_root`, this certificate is
+    /// signed by that server key, and it has not expired at `now`.
+    pub fn validate(&self, trust_root: &[u8; 32], now: DateTime<Utc>) -> bool {
+        self.signer.verify(trust_root)
+            && verify_signature(&self.signer.key, &self.signed_bytes(), &self.signature)
+            && now < self.expires
+    }
+}
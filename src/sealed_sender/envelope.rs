@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypto_utils::{
+        dh::diffie_hellman,
+        encryption::{decrypt_chacha20, encrypt_chacha20},
+        hkdf::derive_secret,
+        seal::{open, seal},
+    },
+    keys::{
+        encrypted_message::EncryptedMessage, identity::IdentityKey,
+        sealed_sender_message::SealedSenderMessage,
+    },
+    sealed_sender::certificate::SenderCertificate,
+};
+
+const STATIC_INFO: &[u8] = b"sealed-sender-static";
+const CONTENT_INFO: &[u8] = b"sealed-sender-content";
+
+/// Identity of the sender of a sealed message, as vouched for by its certificate.
+///
+/// # Fields
+/// - `id`: Sender's user ID.
+/// - `device_id`: Sender's device.
+/// - `name`: Sender's display name.
+/// - `identity_key`: Sender's X25519 identity public key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SenderIdentity {
+    pub id: String,
+    pub device_id: u32,
+    pub name: String,
+    pub identity_key: [u8; 32],
+}
+
+/// Plaintext of the inner layer of a [`SealedSenderMessage`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedSenderContent {
+    certificate: SenderCertificate,
+    sender_name: String,
+    message: EncryptedMessage,
+}
+
+/// Derives the key of the inner layer.
+///
+/// The key depends on `DH(sender_ik, recipient_ik)`, which proves the sender holds the
+/// identity key revealed by the outer layer, and is salted with the outer layer so the
+/// two cannot be mixed and matched.
+fn content_key(static_dh: &[u8; 32], sealed: &SealedSenderMessage) -> [u8; 32] {
+    let salt = [
+        sealed.static_box.ephemeral_public.as_slice(),
+        sealed.static_box.ciphertext.as_slice(),
+    ]
+    .concat();
+    derive_secret(Some(&salt), static_dh, CONTENT_INFO)
+}
+
+/// Wraps a ratchet message so that only the recipient can learn who sent it.
+///
+/// # Parameters
+/// - `sender_ik`: Sender's identity key.
+/// - `certificate`: Sender certificate issued for `sender_ik`.
+/// - `sender_name`: Sender display name to reveal to the recipient.
+/// - `receiver_id`: Recipient's user ID (left in clear for routing).
+/// - `receiver_ik`: Recipient's X25519 identity public key.
+/// - `message`: The [`EncryptedMessage`] to wrap.
+///
+/// # Returns
+/// A [`SealedSenderMessage`] revealing only `receiver_id`.
+pub(crate) fn seal_message(
+    sender_ik: &IdentityKey,
+    certificate: SenderCertificate,
+    sender_name: &str,
+    receiver_id: &str,
+    receiver_ik: &[u8; 32],
+    message: EncryptedMessage,
+) -> SealedSenderMessage {
+    let static_box = seal(receiver_ik, STATIC_INFO, &sender_ik.dh_public);
+    let mut sealed = SealedSenderMessage {
+        receiver: receiver_id.to_string(),
+        static_box,
+        nonce: [0u8; 12],
+        ciphertext: Vec::new(),
+    };
+
+    let static_dh = diffie_hellman(&sender_ik.get_private(), receiver_ik);
+    let content = SealedSenderContent {
+        certificate,
+        sender_name: sender_name.to_string(),
+        message,
+    };
+    let plaintext = serde_json::to_vec(&content).expect("sealed content serialization failed");
+    let (ciphertext, nonce) = encrypt_chacha20(&content_key(&static_dh, &sealed), &plaintext);
+
+    sealed.nonce = nonce;
+    sealed.ciphertext = ciphertext;
+    sealed
+}
+
+/// Opens a [`SealedSenderMessage`] with the recipient's identity key.
+///
+/// # Parameters
+/// - `receiver_ik`: Recipient's identity key.
+/// - `trust_root`: Ed25519 public key of the trust root certificates must chain to.
+/// - `now`: Current time, checked against the certificate expiry.
+/// - `sealed`: The message to open.
+///
+/// # Returns
+/// - `Some((sender, message))` if both layers decrypt, the sender certificate is valid,
+///   and it certifies the identity key that authenticated the inner layer
+/// - `None` otherwise
+pub(crate) fn unseal_message(
+    receiver_ik: &IdentityKey,
+    trust_root: &[u8; 32],
+    now: DateTime<Utc>,
+    sealed: &SealedSenderMessage,
+) -> Option<(SenderIdentity, EncryptedMessage)> {
+    let sender_key: [u8; 32] = open(&receiver_ik.get_private(), STATIC_INFO, &sealed.static_box)?
+        .try_into()
+        .ok()?;
+
+    let static_dh = diffie_hellman(&receiver_ik.get_private(), &sender_key);
+    let plaintext = decrypt_chacha20(
+        &content_key(&static_dh, sealed),
+        &sealed.nonce,
+        &sealed.ciphertext,
+    )
+    .ok()?;
+    let content: SealedSenderContent = serde_json::from_slice(&plaintext).ok()?;
+
+    let certificate = content.certificate;
+    if !certificate.validate(trust_root, now) || certificate.identity_key != sender_key {
+        return None;
+    }
+
+    let sender = SenderIdentity {
+        id: certificate.sender_id,
+        device_id: certificate.device_id,
+        name: content.sender_name,
+        identity_key: sender_key,
+    };
+    Some((sender, content.message))
+}
@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::{CryptoRng, OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypto_utils::{
+        encryption::{decrypt_chacha20, encrypt_chacha20_with_rng},
+        seal::{open, seal_with_rng},
+    },
+    keys::{chain_key::ChainKey, message_key::MessageKey},
+    treekem::{
+        key_schedule::{EpochSecrets, group_context, joiner_secret},
+        messages::{
+            Commit, CommitContent, GroupApplicationMessage, GroupSecrets, KeyPackage,
+            KeyPackageBundle, Proposal, UpdatePath, UpdatePathNode, Welcome,
+        },
+        tree::{RatchetTree, direct_path, is_in_subtree, next_path_secret, node_keypair, sibling},
+    },
+};
+
+/// Maximum number of message keys derived ahead of a sender's chain within one epoch.
+pub(crate) const MAX_SKIPPED_GENERATIONS: u32 = 2000;
+
+const PATH_SECRET_INFO: &[u8] = b"treekem-path-secret";
+const WELCOME_INFO: &[u8] = b"treekem-welcome";
+
+/// Result of processing another member's [`Commit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitOutcome {
+    /// The group moved to the next epoch.
+    Applied,
+    /// The commit removed us from the group; the state can no longer be used.
+    Removed,
+}
+
+/// Receiving side of another member's application chain within the current epoch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReceivingChain {
+    chain: ChainKey,
+    skipped_message_keys: HashMap<u32, MessageKey>,
+}
+
+/// Experimental MLS-style group state based on a TreeKEM ratchet tree.
+///
+/// Each member holds the private keys of the non-blank nodes on its direct path.
+/// A [`Commit`] applies membership proposals and refreshes the committer's path:
+/// every path secret is sealed once per node in the copath resolution, so a commit
+/// costs `O(log n)` encryptions in the common case instead of one pairwise message per
+/// member. Each commit starts a new epoch whose secrets key the application messages.
+///
+/// Simplifications compared to MLS: commits are applied by the committer immediately
+/// (no delivery-service ordering or pending state), added members get their direct path
+/// blanked rather than tracked as unmerged leaves, and only the current epoch can be
+/// decrypted.
+///
+/// # Fields
+/// - `group_id`: Identifier of the group.
+/// - `epoch`: Current epoch number.
+/// - `tree`: Public ratchet tree.
+/// - `own_leaf`: Our leaf index.
+/// - `signature_private`: Ed25519 seed of our leaf's signature key.
+/// - `private_keys`: X25519 private keys we hold, indexed by node.
+/// - `secrets`: Secrets of the current epoch.
+/// - `sending_chain`: Our application chain in the current epoch.
+/// - `receiving_chains`: Other members' application chains, indexed by leaf.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeKemGroup {
+    group_id: String,
+    epoch: u64,
+    tree: RatchetTree,
+    own_leaf: u32,
+    signature_private: [u8; 32],
+    private_keys: HashMap<u32, [u8; 32]>,
+    secrets: EpochSecrets,
+    sending_chain: ChainKey,
+    receiving_chains: HashMap<u32, ReceivingChain>,
+}
+
+impl TreeKemGroup {
+    /// Creates a one-member group at epoch 0 from our key package.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the new group.
+    /// - `bundle`: Our key package and its private keys.
+    pub fn create(group_id: &str, bundle: &KeyPackageBundle) -> Self {
+        Self::create_with_rng(group_id, bundle, &mut OsRng)
+    }
+
+    /// Creates a one-member group at epoch 0, drawing the initial secret from the given RNG.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the new group.
+    /// - `bundle`: Our key package and its private keys.
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible epoch secrets.
+    pub fn create_with_rng<R: CryptoRng + RngCore>(
+        group_id: &str,
+        bundle: &KeyPackageBundle,
+        rng: &mut R,
+    ) -> Self {
+        let tree = RatchetTree::new(bundle.key_package.leaf.clone());
+
+        let mut initial_secret = [0u8; 32];
+        rng.fill_bytes(&mut initial_secret);
+        let secrets =
+            EpochSecrets::from_joiner(&initial_secret, &group_context(group_id, 0, &tree.hash()));
+
+        Self::from_parts(
+            group_id.to_string(),
+            0,
+            tree,
+            0,
+            bundle.signature_private(),
+            HashMap::from([(0, bundle.encryption_private())]),
+            secrets,
+        )
+    }
+
+    /// Joins a group from a [`Welcome`] produced by the commit that added us.
+    ///
+    /// # Arguments
+    /// - `welcome`: The Welcome addressed to us.
+    /// - `bundle`: The key package bundle the adding member used.
+    ///
+    /// # Returns
+    /// The group state at the new epoch, or `None` if the Welcome cannot be opened or is
+    /// inconsistent with the tree it carries.
+    pub fn join(welcome: &Welcome, bundle: &KeyPackageBundle) -> Option<Self> {
+        let bytes = open(&bundle.encryption_private(), WELCOME_INFO, &welcome.secrets)?;
+        let secrets: GroupSecrets = serde_json::from_slice(&bytes).ok()?;
+
+        let own_leaf = secrets
+            .tree
+            .find_member(&bundle.key_package.leaf.member_id)?;
+        if secrets.tree.leaf(own_leaf)? != &bundle.key_package.leaf {
+            return None;
+        }
+
+        let mut private_keys = HashMap::from([(2 * own_leaf, bundle.encryption_private())]);
+        if let Some(path_secret) = secrets.path_secret {
+            let path = direct_path(2 * secrets.committer, secrets.tree.leaf_count());
+            let start = path
+                .iter()
+                .position(|node| is_in_subtree(*node, 2 * own_leaf))?;
+
+            let mut secret = path_secret;
+            for node in &path[start..] {
+                let (private, public) = node_keypair(&secret);
+                if secrets.tree.node(*node)?.public_key() != public {
+                    return None;
+                }
+                private_keys.insert(*node, private);
+                secret = next_path_secret(&secret);
+            }
+        }
+
+        let context = group_context(&secrets.group_id, secrets.epoch, &secrets.tree.hash());
+        let epoch_secrets = EpochSecrets::from_joiner(&secrets.joiner_secret, &context);
+
+        Some(Self::from_parts(
+            secrets.group_id,
+            secrets.epoch,
+            secrets.tree,
+            own_leaf,
+            bundle.signature_private(),
+            private_keys,
+            epoch_secrets,
+        ))
+    }
+
+    /// Returns the group identifier.
+    pub fn group_id(&self) -> &str {
+        &self.group_id
+    }
+
+    /// Returns the current epoch number.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the member IDs currently in the tree.
+    pub fn members(&self) -> Vec<String> {
+        self.tree
+            .members()
+            .into_iter()
+            .map(|(_, leaf)| leaf.member_id.clone())
+            .collect()
+    }
+
+    /// Commits the addition of new members.
+    ///
+    /// # Returns
+    /// The [`Commit`] for existing members and one [`Welcome`] per new member, or `None`
+    /// if a key package is invalid or its member is already in the group.
+    pub fn add_members(&mut self, key_packages: &[KeyPackage]) -> Option<(Commit, Vec<Welcome>)> {
+        let proposals = key_packages.iter().cloned().map(Proposal::Add).collect();
+        self.commit(proposals)
+    }
+
+    /// Commits the removal of members; they cannot derive any later epoch secret.
+    ///
+    /// # Returns
+    /// The [`Commit`] for the remaining members, or `None` if a member is unknown or is us.
+    pub fn remove_members(&mut self, member_ids: &[&str]) -> Option<Commit> {
+        let proposals = member_ids
+            .iter()
+            .map(|id| Proposal::Remove(id.to_string()))
+            .collect();
+        self.commit(proposals).map(|(commit, _)| commit)
+    }
+
+    /// Commits a refresh of our leaf and direct path keys (post-compromise security).
+    pub fn update(&mut self) -> Commit {
+        self.update_with_rng(&mut OsRng)
+    }
+
+    /// Commits a refresh of our leaf and direct path keys drawn from the given RNG.
+    ///
+    /// # Arguments
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible commits.
+    pub fn update_with_rng<R: CryptoRng + RngCore>(&mut self, rng: &mut R) -> Commit {
+        self.commit_with_rng(Vec::new(), rng)
+            .map(|(commit, _)| commit)
+            .expect("empty commit cannot fail")
+    }
+
+    /// Applies proposals, refreshes our direct path and moves to the next epoch.
+    ///
+    /// # Arguments
+    /// - `proposals`: Membership changes to apply.
+    ///
+    /// # Returns
+    /// The signed [`Commit`] and the [`Welcome`]s for added members, or `None` if a
+    /// proposal is invalid.
+    pub fn commit(&mut self, proposals: Vec<Proposal>) -> Option<(Commit, Vec<Welcome>)> {
+        self.commit_with_rng(proposals, &mut OsRng)
+    }
+
+    /// Applies proposals and refreshes our direct path with a leaf secret drawn from the
+    /// given RNG, which also seals the path secrets and Welcomes.
+    ///
+    /// # Arguments
+    /// - `proposals`: Membership changes to apply.
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible commits.
+    ///
+    /// # Returns
+    /// The signed [`Commit`] and the [`Welcome`]s for added members, or `None` if a
+    /// proposal is invalid.
+    pub fn commit_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        proposals: Vec<Proposal>,
+        rng: &mut R,
+    ) -> Option<(Commit, Vec<Welcome>)> {
+        let own_id = self.tree.leaf(self.own_leaf)?.member_id.clone();
+        if proposals
+            .iter()
+            .any(|p| matches!(p, Proposal::Remove(id) if *id == own_id))
+        {
+            return None;
+        }
+
+        let mut tree = self.tree.clone();
+        let joiners = apply_proposals(&mut tree, &proposals)?;
+        let exclude: Vec<u32> = joiners.iter().map(|(leaf, _)| *leaf).collect();
+
+        let mut leaf_secret = [0u8; 32];
+        rng.fill_bytes(&mut leaf_secret);
+        let (leaf_private, leaf_key) = node_keypair(&leaf_secret);
+
+        let own_node = 2 * self.own_leaf;
+        let path = direct_path(own_node, tree.leaf_count());
+        let mut new_private = HashMap::from([(own_node, leaf_private)]);
+        let mut path_secrets = HashMap::new();
+        let mut nodes = Vec::new();
+
+        let mut secret = leaf_secret;
+        let mut child = own_node;
+        for node in &path {
+            secret = next_path_secret(&secret);
+            let (private, public_key) = node_keypair(&secret);
+
+            let encrypted_path_secrets = tree
+                .resolution(sibling(child), &exclude)
+                .into_iter()
+                .filter_map(|r| tree.node(r).map(|n| n.public_key()))
+                .map(|public| seal_with_rng(&public, PATH_SECRET_INFO, &secret, rng))
+                .collect();
+
+            nodes.push(UpdatePathNode {
+                public_key,
+                encrypted_path_secrets,
+            });
+            new_private.insert(*node, private);
+            path_secrets.insert(*node, secret);
+            child = *node;
+        }
+        let commit_secret = next_path_secret(&secret);
+
+        let path_keys: Vec<[u8; 32]> = nodes.iter().map(|n| n.public_key).collect();
+        tree.apply_path(self.own_leaf, leaf_key, &path_keys);
+
+        let new_epoch = self.epoch + 1;
+        let joiner = joiner_secret(self.secrets.init_secret(), &commit_secret);
+        let secrets = EpochSecrets::from_joiner(
+            &joiner,
+            &group_context(&self.group_id, new_epoch, &tree.hash()),
+        );
+
+        let content = CommitContent {
+            group_id: self.group_id.clone(),
+            epoch: self.epoch,
+            committer: self.own_leaf,
+            proposals,
+            path: UpdatePath { leaf_key, nodes },
+        };
+        let content_bytes = serde_json::to_vec(&content).expect("commit serialization failed");
+        let signature = SigningKey::from_bytes(&self.signature_private)
+            .sign(&content_bytes)
+            .to_vec();
+        let commit = Commit {
+            confirmation_tag: secrets.confirmation_tag(&content_bytes),
+            content,
+            signature,
+        };
+
+        let welcomes = joiners
+            .iter()
+            .map(|(leaf, key_package)| {
+                let group_secrets = GroupSecrets {
+                    group_id: self.group_id.clone(),
+                    epoch: new_epoch,
+                    tree: tree.clone(),
+                    joiner_secret: joiner,
+                    committer: self.own_leaf,
+                    path_secret: path
+                        .iter()
+                        .find(|node| is_in_subtree(**node, 2 * leaf))
+                        .and_then(|node| path_secrets.get(node).copied()),
+                };
+                let bytes =
+                    serde_json::to_vec(&group_secrets).expect("welcome serialization failed");
+                Welcome {
+                    member_id: key_package.leaf.member_id.clone(),
+                    secrets: seal_with_rng(
+                        &key_package.leaf.encryption_key,
+                        WELCOME_INFO,
+                        &bytes,
+                        rng,
+                    ),
+                }
+            })
+            .collect();
+
+        self.advance_epoch(tree, new_private, secrets);
+        Some((commit, welcomes))
+    }
+
+    /// Processes a commit made by another member.
+    ///
+    /// Verifies the committer's signature, rejects commits removing their own committer
+    /// (as MLS does), applies the proposals, decrypts the path secret meant for us, checks
+    /// every derived public key against the update path, and finally verifies the
+    /// confirmation tag before moving to the next epoch. Nothing is modified if any check
+    /// fails.
+    ///
+    /// # Returns
+    /// - `Some(CommitOutcome::Applied)` if the group moved to the next epoch
+    /// - `Some(CommitOutcome::Removed)` if the commit removed us
+    /// - `None` if the commit is invalid, removes its committer, or is not for the current
+    ///   epoch
+    pub fn process_commit(&mut self, commit: &Commit) -> Option<CommitOutcome> {
+        let content = &commit.content;
+        if content.group_id != self.group_id
+            || content.epoch != self.epoch
+            || content.committer == self.own_leaf
+        {
+            return None;
+        }
+
+        let committer = self.tree.leaf(content.committer)?;
+        let content_bytes = serde_json::to_vec(content).ok()?;
+        let verifying_key = VerifyingKey::from_bytes(&committer.signature_key).ok()?;
+        let signature = Signature::from_slice(&commit.signature).ok()?;
+        verifying_key.verify(&content_bytes, &signature).ok()?;
+        if content
+            .proposals
+            .iter()
+            .any(|p| matches!(p, Proposal::Remove(id) if *id == committer.member_id))
+        {
+            return None;
+        }
+
+        let own_id = self.tree.leaf(self.own_leaf)?.member_id.clone();
+        let mut tree = self.tree.clone();
+        let joiners = apply_proposals(&mut tree, &content.proposals)?;
+        if tree.find_member(&own_id) != Some(self.own_leaf) {
+            return Some(CommitOutcome::Removed);
+        }
+        let exclude: Vec<u32> = joiners.iter().map(|(leaf, _)| *leaf).collect();
+
+        let committer_node = 2 * content.committer;
+        let path = direct_path(committer_node, tree.leaf_count());
+        let update_nodes = &content.path.nodes;
+        if update_nodes.len() != path.len() {
+            return None;
+        }
+
+        let start = path
+            .iter()
+            .position(|node| is_in_subtree(*node, 2 * self.own_leaf))?;
+        let child = if start == 0 {
+            committer_node
+        } else {
+            path[start - 1]
+        };
+        let resolution = tree.resolution(sibling(child), &exclude);
+        let position = resolution
+            .iter()
+            .position(|node| self.private_keys.contains_key(node))?;
+        let sealed = update_nodes[start].encrypted_path_secrets.get(position)?;
+        let decrypted = open(
+            &self.private_keys[&resolution[position]],
+            PATH_SECRET_INFO,
+            sealed,
+        )?;
+        let mut secret: [u8; 32] = decrypted.try_into().ok()?;
+
+        let mut new_private = HashMap::new();
+        for (node, update) in path.iter().zip(update_nodes).skip(start) {
+            let (private, public) = node_keypair(&secret);
+            if public != update.public_key {
+                return None;
+            }
+            new_private.insert(*node, private);
+            secret = next_path_secret(&secret);
+        }
+        let commit_secret = secret;
+
+        let path_keys: Vec<[u8; 32]> = update_nodes.iter().map(|n| n.public_key).collect();
+        tree.apply_path(content.committer, content.path.leaf_key, &path_keys);
+
+        let new_epoch = self.epoch + 1;
+        let joiner = joiner_secret(self.secrets.init_secret(), &commit_secret);
+        let secrets = EpochSecrets::from_joiner(
+            &joiner,
+            &group_context(&self.group_id, new_epoch, &tree.hash()),
+        );
+        if !secrets.verify_confirmation_tag(&content_bytes, &commit.confirmation_tag) {
+            return None;
+        }
+
+        self.advance_epoch(tree, new_private, secrets);
+        Some(CommitOutcome::Applied)
+    }
+
+    /// Encrypts and signs an application message under the current epoch.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> GroupApplicationMessage {
+        self.encrypt_with_rng(plaintext, &mut OsRng)
+    }
+
+    /// Encrypts and signs an application message, drawing the nonce from the given RNG.
+    ///
+    /// # Arguments
+    /// - `plaintext`: Payload to encrypt.
+    /// - `rng`: Source of the nonce. A seeded RNG yields reproducible ciphertexts.
+    pub fn encrypt_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        plaintext: &[u8],
+        rng: &mut R,
+    ) -> GroupApplicationMessage {
+        let (next_ck, message_key) = self.sending_chain.derive_next();
+        self.sending_chain = next_ck;
+
+        let (ciphertext, nonce) = encrypt_chacha20_with_rng(message_key.get_key(), plaintext, rng);
+
+        let mut msg = GroupApplicationMessage {
+            group_id: self.group_id.clone(),
+            epoch: self.epoch,
+            sender: self.own_leaf,
+            generation: message_key.get_index(),
+            nonce,
+            ciphertext,
+            signature: Vec::new(),
+        };
+        msg.signature = SigningKey::from_bytes(&self.signature_private)
+            .sign(&msg.signed_bytes())
+            .to_vec();
+        msg
+    }
+
+    /// Verifies and decrypts an application message from the current epoch.
+    ///
+    /// # Returns
+    /// - `Some(plaintext)` if the message is authentic and decrypts
+    /// - `None` if it belongs to another epoch, the sender is unknown, the signature is
+    ///   invalid, the message key was already consumed, or decryption fails
+    pub fn decrypt(&mut self, msg: &GroupApplicationMessage) -> Option<Vec<u8>> {
+        if msg.group_id != self.group_id || msg.epoch != self.epoch || msg.sender == self.own_leaf {
+            return None;
+        }
+
+        let sender = self.tree.leaf(msg.sender)?;
+        let verifying_key = VerifyingKey::from_bytes(&sender.signature_key).ok()?;
+        let signature = Signature::from_slice(&msg.signature).ok()?;
+        verifying_key.verify(&msg.signed_bytes(), &signature).ok()?;
+
+        let initial_key = self.secrets.sender_chain_key(msg.sender);
+        let receiving = self
+            .receiving_chains
+            .entry(msg.sender)
+            .or_insert_with(|| ReceivingChain {
+                chain: ChainKey::new(initial_key, 0),
+                skipped_message_keys: HashMap::new(),
+            });
+
+        if msg.generation < receiving.chain.get_index() {
+            let message_key = receiving.skipped_message_keys.remove(&msg.generation)?;
+            return decrypt_chacha20(message_key.get_key(), &msg.nonce, &msg.ciphertext).ok();
+        }
+
+        if msg.generation - receiving.chain.get_index() > MAX_SKIPPED_GENERATIONS {
+            return None;
+        }
+
+        while receiving.chain.get_index() < msg.generation {
+            let (next_ck, skipped_key) = receiving.chain.derive_next();
+            receiving
+                .skipped_message_keys
+                .insert(skipped_key.get_index(), skipped_key);
+            receiving.chain = next_ck;
+        }
+
+        let (next_ck, message_key) = receiving.chain.derive_next();
+        receiving.chain = next_ck;
+
+        decrypt_chacha20(message_key.get_key(), &msg.nonce, &msg.ciphertext).ok()
+    }
+
+    fn from_parts(
+        group_id: String,
+        epoch: u64,
+        tree: RatchetTree,
+        own_leaf: u32,
+        signature_private: [u8; 32],
+        private_keys: HashMap<u32, [u8; 32]>,
+        secrets: EpochSecrets,
+    ) -> Self {
+        let sending_chain = ChainKey::new(secrets.sender_chain_key(own_leaf), 0);
+        Self {
+            group_id,
+            epoch,
+            tree,
+            own_leaf,
+            signature_private,
+            private_keys,
+            secrets,
+            sending_chain,
+            receiving_chains: HashMap::new(),
+        }
+    }
+
+    /// Installs the tree and secrets of the next epoch and resets the application chains.
+    fn advance_epoch(
+        &mut self,
+        tree: RatchetTree,
+        new_private: HashMap<u32, [u8; 32]>,
+        secrets: EpochSecrets,
+    ) {
+        self.private_keys
+            .retain(|node, _| tree.node(*node).is_some());
+        self.private_keys.extend(new_private);
+        self.tree = tree;
+        self.epoch += 1;
+        self.sending_chain = ChainKey::new(secrets.sender_chain_key(self.own_leaf), 0);
+        self.secrets = secrets;
+        self.receiving_chains.clear();
+    }
+}
+
+/// Applies removals then additions to `tree`.
+///
+/// # Returns
+/// The `(leaf_index, key_package)` of every added member, or `None` if a key package is
+/// invalid, an added member already exists, or a removed member is unknown.
+fn apply_proposals(
+    tree: &mut RatchetTree,
+    proposals: &[Proposal],
+) -> Option<Vec<(u32, KeyPackage)>> {
+    for proposal in proposals {
+        if let Proposal::Remove(member_id) = proposal {
+            let leaf = tree.find_member(member_id)?;
+            tree.remove_leaf(leaf);
+        }
+    }
+
+    let mut joiners = Vec::new();
+    for proposal in proposals {
+        if let Proposal::Add(key_package) = proposal {
+            if !key_package.verify() || tree.find_member(&key_package.leaf.member_id).is_some() {
+                return None;
+            }
+            let leaf = tree.add_leaf(key_package.leaf.clone());
+            joiners.push((leaf, key_package.clone()));
+        }
+    }
+    Some(joiners)
+}
@@ -0,0 +1,81 @@
+use hkdf::hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::crypto_utils::hkdf::derive_secret;
+
+/// Serializes the group context binding epoch secrets to a group, epoch and tree.
+///
+/// # Parameters
+/// - `group_id`: Identifier of the group.
+/// - `epoch`: Epoch number.
+/// - `tree_hash`: Hash of the public ratchet tree at that epoch.
+pub(crate) fn group_context(group_id: &str, epoch: u64, tree_hash: &[u8; 32]) -> Vec<u8> {
+    let mut context = Vec::new();
+    context.extend_from_slice(&(group_id.len() as u32).to_be_bytes());
+    context.extend_from_slice(group_id.as_bytes());
+    context.extend_from_slice(&epoch.to_be_bytes());
+    context.extend_from_slice(tree_hash);
+    context
+}
+
+/// Combines the previous epoch's init secret with a commit secret.
+///
+/// The result is shared with members joining at the new epoch through their Welcome.
+pub(crate) fn joiner_secret(init_secret: &[u8; 32], commit_secret: &[u8; 32]) -> [u8; 32] {
+    derive_secret(Some(init_secret), commit_secret, b"treekem-joiner")
+}
+
+/// Secrets derived for one epoch of a TreeKEM group.
+///
+/// # Fields
+/// - `init_secret`: Chained into the next epoch's key schedule.
+/// - `encryption_secret`: Root of the per-sender application message chains.
+/// - `confirmation_key`: Keys the confirmation tag of the commit that created the epoch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct EpochSecrets {
+    init_secret: [u8; 32],
+    encryption_secret: [u8; 32],
+    confirmation_key: [u8; 32],
+}
+
+impl EpochSecrets {
+    /// Runs the key schedule for an epoch from its joiner secret and group context.
+    pub(crate) fn from_joiner(joiner_secret: &[u8; 32], group_context: &[u8]) -> Self {
+        let epoch_secret = derive_secret(Some(group_context), joiner_secret, b"treekem-epoch");
+
+        Self {
+            init_secret: derive_secret(None, &epoch_secret, b"treekem-init"),
+            encryption_secret: derive_secret(None, &epoch_secret, b"treekem-encryption"),
+            confirmation_key: derive_secret(None, &epoch_secret, b"treekem-confirm"),
+        }
+    }
+
+    /// Returns the init secret feeding the next epoch.
+    pub(crate) fn init_secret(&self) -> &[u8; 32] {
+        &self.init_secret
+    }
+
+    /// Derives the initial application chain key of the member at `leaf_index`.
+    pub(crate) fn sender_chain_key(&self, leaf_index: u32) -> [u8; 32] {
+        derive_secret(
+            Some(&leaf_index.to_be_bytes()),
+            &self.encryption_secret,
+            b"treekem-sender",
+        )
+    }
+
+    /// Computes the confirmation tag over a serialized commit.
+    pub(crate) fn confirmation_tag(&self, commit_bytes: &[u8]) -> [u8; 32] {
+        let mut hmac = Hmac::<Sha256>::new_from_slice(&self.confirmation_key).unwrap();
+        hmac.update(commit_bytes);
+        hmac.finalize().into_bytes().into()
+    }
+
+    /// Checks a confirmation tag in constant time.
+    pub(crate) fn verify_confirmation_tag(&self, commit_bytes: &[u8], tag: &[u8; 32]) -> bool {
+        let mut hmac = Hmac::<Sha256>::new_from_slice(&self.confirmation_key).unwrap();
+        hmac.update(commit_bytes);
+        hmac.verify_slice(tag).is_ok()
+    }
+}
@@ -0,0 +1,220 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::{CryptoRng, OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto_utils::seal::SealedBox;
+use crate::keys::ephemeral_key::EphemeralKey;
+use crate::treekem::tree::{LeafNode, RatchetTree};
+
+/// Publicly advertised material allowing a member to be added to a TreeKEM group.
+///
+/// # Fields
+/// - `leaf`: The leaf the member will occupy (identity, encryption and signature keys).
+/// - `signature`: Ed25519 self-signature over `leaf`, made with `leaf.signature_key`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyPackage {
+    pub leaf: LeafNode,
+    pub signature: Vec<u8>,
+}
+
+impl KeyPackage {
+    /// Checks the self-signature of the key package.
+    pub fn verify(&self) -> bool {
+        let Ok(verifying_key) = VerifyingKey::from_bytes(&self.leaf.signature_key) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(&self.signature) else {
+            return false;
+        };
+        let bytes = serde_json::to_vec(&self.leaf).expect("leaf serialization failed");
+        verifying_key.verify(&bytes, &signature).is_ok()
+    }
+}
+
+/// A [`KeyPackage`] together with its private keys, kept by the future member.
+///
+/// # Fields
+/// - `key_package`: The public, signed key package.
+/// - `encryption_private`: X25519 private key matching `leaf.encryption_key`.
+/// - `signature_private`: Ed25519 seed matching `leaf.signature_key`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyPackageBundle {
+    pub key_package: KeyPackage,
+    encryption_private: [u8; 32],
+    signature_private: [u8; 32],
+}
+
+impl KeyPackageBundle {
+    /// Generates fresh encryption and signature keys for `member_id` and signs the key package.
+    pub fn new(member_id: &str) -> Self {
+        Self::new_with_rng(member_id, &mut OsRng)
+    }
+
+    /// Generates the key package for `member_id` from the given RNG.
+    ///
+    /// # Arguments
+    /// - `member_id`: Identifier of the member the key package belongs to.
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible keys.
+    pub fn new_with_rng<R: CryptoRng + RngCore>(member_id: &str, rng: &mut R) -> Self {
+        let encryption = EphemeralKey::new_with_rng(rng);
+
+        let mut signature_private = [0u8; 32];
+        rng.fill_bytes(&mut signature_private);
+        let signing_key = SigningKey::from_bytes(&signature_private);
+
+        let leaf = LeafNode {
+            member_id: member_id.to_string(),
+            encryption_key: encryption.public,
+            signature_key: signing_key.verifying_key().to_bytes(),
+        };
+        let bytes = serde_json::to_vec(&leaf).expect("leaf serialization failed");
+        let signature = signing_key.sign(&bytes).to_vec();
+
+        Self {
+            key_package: KeyPackage { leaf, signature },
+            encryption_private: encryption.get_private(),
+            signature_private,
+        }
+    }
+
+    /// Returns the X25519 private key of the key package.
+    ///
+    /// > ⚠️ This function exposes sensitive material. Handle with care.
+    pub(crate) fn encryption_private(&self) -> [u8; 32] {
+        self.encryption_private
+    }
+
+    /// Returns the Ed25519 seed of the key package.
+    ///
+    /// > ⚠️ This function exposes sensitive material. Handle with care.
+    pub(crate) fn signature_private(&self) -> [u8; 32] {
+        self.signature_private
+    }
+}
+
+/// A change to the group membership carried by a [`Commit`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Proposal {
+    /// Add the member described by the key package.
+    Add(KeyPackage),
+    /// Remove the member with the given ID.
+    Remove(String),
+}
+
+/// New public key of one node of the committer's direct path.
+///
+/// # Fields
+/// - `public_key`: The node's new X25519 public key.
+/// - `encrypted_path_secrets`: The node's path secret, sealed to every node in the
+///   resolution of its copath child, in resolution order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePathNode {
+    pub public_key: [u8; 32],
+    pub encrypted_path_secrets: Vec<SealedBox>,
+}
+
+/// Fresh key material for the committer's leaf and direct path.
+///
+/// # Fields
+/// - `leaf_key`: New X25519 encryption key of the committer's leaf.
+/// - `nodes`: One entry per node of the committer's direct path, bottom-up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePath {
+    pub leaf_key: [u8; 32],
+    pub nodes: Vec<UpdatePathNode>,
+}
+
+/// The signed part of a [`Commit`].
+///
+/// # Fields
+/// - `group_id`: Identifier of the group.
+/// - `epoch`: Epoch the commit was created in (the group moves to `epoch + 1`).
+/// - `committer`: Leaf index of the committing member.
+/// - `proposals`: Membership changes applied before the path update.
+/// - `path`: The committer's new leaf and direct-path keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitContent {
+    pub group_id: String,
+    pub epoch: u64,
+    pub committer: u32,
+    pub proposals: Vec<Proposal>,
+    pub path: UpdatePath,
+}
+
+/// A handshake message moving the group to the next epoch.
+///
+/// # Fields
+/// - `content`: The commit itself.
+/// - `signature`: Ed25519 signature over `content` by the committer's leaf signature key.
+/// - `confirmation_tag`: HMAC over `content` with the new epoch's confirmation key,
+///   proving the committer derived the same epoch secrets as the receivers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Commit {
+    pub content: CommitContent,
+    pub signature: Vec<u8>,
+    pub confirmation_tag: [u8; 32],
+}
+
+/// Secrets a new member needs to join the group at a given epoch.
+///
+/// Serialized and sealed to the joiner's key package inside a [`Welcome`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct GroupSecrets {
+    pub(crate) group_id: String,
+    pub(crate) epoch: u64,
+    pub(crate) tree: RatchetTree,
+    pub(crate) joiner_secret: [u8; 32],
+    pub(crate) committer: u32,
+    pub(crate) path_secret: Option<[u8; 32]>,
+}
+
+/// Invitation sent to a member added by a commit.
+///
+/// # Fields
+/// - `member_id`: Identifier of the joining member.
+/// - `secrets`: The serialized group secrets, sealed to the joiner's key package.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Welcome {
+    pub member_id: String,
+    pub secrets: SealedBox,
+}
+
+/// An application message encrypted under the sender's per-epoch chain.
+///
+/// # Fields
+/// - `group_id`: Identifier of the group.
+/// - `epoch`: Epoch whose secrets were used.
+/// - `sender`: Leaf index of the sender.
+/// - `generation`: Index of the message key within the sender's chain.
+/// - `nonce`: A 12-byte nonce for AEAD encryption.
+/// - `ciphertext`: The encrypted payload.
+/// - `signature`: Ed25519 signature over all the fields above by the sender's leaf key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupApplicationMessage {
+    pub group_id: String,
+    pub epoch: u64,
+    pub sender: u32,
+    pub generation: u32,
+    pub nonce: [u8; 12],
+    pub ciphertext: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl GroupApplicationMessage {
+    /// Returns the byte string covered by the sender's signature.
+    ///
+    /// # Returns
+    /// `group_id || epoch || sender || generation || nonce || ciphertext`, with integers in
+    /// big-endian and `group_id` prefixed by its big-endian `u32` length.
+    pub(crate) fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(self.group_id.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.group_id.as_bytes());
+        bytes.extend_from_slice(&self.epoch.to_be_bytes());
+        bytes.extend_from_slice(&self.sender.to_be_bytes());
+        bytes.extend_from_slice(&self.generation.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+}
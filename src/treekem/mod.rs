@@ -0,0 +1,4 @@
+pub mod group;
+pub mod key_schedule;
+pub mod messages;
+pub mod tree;
@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::crypto_utils::hkdf::derive_secret;
+
+/// Number of trailing one bits of a node index, i.e. its height in the tree (leaves are 0).
+pub(crate) fn level(x: u32) -> u32 {
+    x.trailing_ones()
+}
+
+/// Index of the root node of a complete tree with `n_leaves` leaves (a power of two).
+pub(crate) fn root(n_leaves: u32) -> u32 {
+    n_leaves - 1
+}
+
+/// Left child of a parent node.
+pub(crate) fn left(x: u32) -> u32 {
+    let k = level(x);
+    x ^ (1 << (k - 1))
+}
+
+/// Right child of a parent node.
+pub(crate) fn right(x: u32) -> u32 {
+    let k = level(x);
+    x ^ (3 << (k - 1))
+}
+
+/// Parent of a non-root node.
+pub(crate) fn parent(x: u32) -> u32 {
+    let k = level(x);
+    let b = (x >> (k + 1)) & 1;
+    (x | (1 << k)) ^ (b << (k + 1))
+}
+
+/// The other child of `x`'s parent.
+pub(crate) fn sibling(x: u32) -> u32 {
+    let p = parent(x);
+    if x < p { right(p) } else { left(p) }
+}
+
+/// Ancestors of `x`, from its parent up to and including the root.
+pub(crate) fn direct_path(x: u32, n_leaves: u32) -> Vec<u32> {
+    let r = root(n_leaves);
+    let mut path = Vec::new();
+    let mut node = x;
+    while node != r {
+        node = parent(node);
+        path.push(node);
+    }
+    path
+}
+
+/// Returns `true` if `ancestor` is `x` itself or one of its ancestors.
+pub(crate) fn is_in_subtree(ancestor: u32, x: u32) -> bool {
+    let k = level(ancestor);
+    level(x) <= k && (x >> (k + 1)) == (ancestor >> (k + 1))
+}
+
+/// Derives the X25519 key pair of a tree node from its path secret.
+///
+/// # Returns
+/// A tuple `(private, public)`.
+pub(crate) fn node_keypair(path_secret: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+    let private = derive_secret(None, path_secret, b"treekem-node");
+    let public = PublicKey::from(&StaticSecret::from(private));
+    (private, *public.as_bytes())
+}
+
+/// Derives the path secret of the next node up the direct path.
+pub(crate) fn next_path_secret(path_secret: &[u8; 32]) -> [u8; 32] {
+    derive_secret(None, path_secret, b"treekem-path")
+}
+
+/// Public content of a leaf: a member and its current keys.
+///
+/// # Fields
+/// - `member_id`: Identifier of the member occupying the leaf.
+/// - `encryption_key`: X25519 public key path secrets are encrypted to.
+/// - `signature_key`: Ed25519 public key verifying the member's commits and messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafNode {
+    pub member_id: String,
+    pub encryption_key: [u8; 32],
+    pub signature_key: [u8; 32],
+}
+
+/// A non-blank node of the ratchet tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TreeNode {
+    Leaf(LeafNode),
+    Parent { public_key: [u8; 32] },
+}
+
+impl TreeNode {
+    /// Returns the X25519 public key held by this node.
+    pub fn public_key(&self) -> [u8; 32] {
+        match self {
+            TreeNode::Leaf(leaf) => leaf.encryption_key,
+            TreeNode::Parent { public_key } => *public_key,
+        }
+    }
+}
+
+/// Public state of a left-balanced binary ratchet tree, in array representation.
+///
+/// Leaves sit at even indices and parents at odd indices; blank nodes are `None`.
+/// The number of leaves is always a power of two, and the tree doubles in size when
+/// a member is added and no blank leaf is left.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RatchetTree {
+    nodes: Vec<Option<TreeNode>>,
+}
+
+impl RatchetTree {
+    /// Creates a one-leaf tree holding the group creator.
+    pub(crate) fn new(creator: LeafNode) -> Self {
+        Self {
+            nodes: vec![Some(TreeNode::Leaf(creator))],
+        }
+    }
+
+    /// Number of leaves (blank or not).
+    pub fn leaf_count(&self) -> u32 {
+        (self.nodes.len() as u32).div_ceil(2)
+    }
+
+    /// Returns the node at `index`, or `None` if it is blank or out of range.
+    pub fn node(&self, index: u32) -> Option<&TreeNode> {
+        self.nodes.get(index as usize).and_then(|n| n.as_ref())
+    }
+
+    /// Returns the leaf at leaf position `leaf_index`, if occupied.
+    pub fn leaf(&self, leaf_index: u32) -> Option<&LeafNode> {
+        match self.node(2 * leaf_index) {
+            Some(TreeNode::Leaf(leaf)) => Some(leaf),
+            _ => None,
+        }
+    }
+
+    /// Returns every occupied leaf as `(leaf_index, leaf)` pairs.
+    pub fn members(&self) -> Vec<(u32, &LeafNode)> {
+        (0..self.leaf_count())
+            .filter_map(|i| self.leaf(i).map(|leaf| (i, leaf)))
+            .collect()
+    }
+
+    /// Finds the leaf index occupied by `member_id`.
+    pub fn find_member(&self, member_id: &str) -> Option<u32> {
+        self.members()
+            .into_iter()
+            .find(|(_, leaf)| leaf.member_id == member_id)
+            .map(|(i, _)| i)
+    }
+
+    /// Places a new member in the leftmost blank leaf, growing the tree if needed.
+    ///
+    /// The new leaf's direct path is blanked: its former keys are unknown to the joiner.
+    ///
+    /// # Returns
+    /// The leaf index assigned to the new member.
+    pub(crate) fn add_leaf(&mut self, leaf: LeafNode) -> u32 {
+        let leaf_index = match (0..self.leaf_count()).find(|i| self.leaf(*i).is_none()) {
+            Some(i) => i,
+            None => {
+                let n = self.leaf_count();
+                self.nodes.resize(2 * self.nodes.len() + 1, None);
+                n
+            }
+        };
+        self.nodes[2 * leaf_index as usize] = Some(TreeNode::Leaf(leaf));
+        self.blank_direct_path(leaf_index);
+        leaf_index
+    }
+
+    /// Blanks a member's leaf and its whole direct path.
+    pub(crate) fn remove_leaf(&mut self, leaf_index: u32) {
+        self.nodes[2 * leaf_index as usize] = None;
+        self.blank_direct_path(leaf_index);
+    }
+
+    /// Installs the public keys of a freshly updated direct path.
+    ///
+    /// # Arguments
+    /// - `leaf_index`: Leaf of the committer.
+    /// - `leaf_key`: New encryption key of the committer's leaf.
+    /// - `path_keys`: New public keys of the committer's direct path, bottom-up.
+    pub(crate) fn apply_path(
+        &mut self,
+        leaf_index: u32,
+        leaf_key: [u8; 32],
+        path_keys: &[[u8; 32]],
+    ) {
+        if let Some(Some(TreeNode::Leaf(leaf))) = self.nodes.get_mut(2 * leaf_index as usize) {
+            leaf.encryption_key = leaf_key;
+        }
+        for (node, public_key) in direct_path(2 * leaf_index, self.leaf_count())
+            .into_iter()
+            .zip(path_keys)
+        {
+            self.nodes[node as usize] = Some(TreeNode::Parent {
+                public_key: *public_key,
+            });
+        }
+    }
+
+    /// Computes the resolution of a node: the minimal set of non-blank nodes covering
+    /// all the leaves below it.
+    ///
+    /// # Arguments
+    /// - `x`: Node index.
+    /// - `exclude_leaves`: Leaf indices left out of the resolution (e.g. members being
+    ///   added by the current commit, who learn the path secret from their Welcome).
+    pub(crate) fn resolution(&self, x: u32, exclude_leaves: &[u32]) -> Vec<u32> {
+        match self.node(x) {
+            Some(_) if level(x) == 0 && exclude_leaves.contains(&(x / 2)) => Vec::new(),
+            Some(_) => vec![x],
+            None if level(x) == 0 => Vec::new(),
+            None => {
+                let mut res = self.resolution(left(x), exclude_leaves);
+                res.extend(self.resolution(right(x), exclude_leaves));
+                res
+            }
+        }
+    }
+
+    /// Hash of the whole public tree, binding epoch secrets to the exact membership.
+    pub(crate) fn hash(&self) -> [u8; 32] {
+        let bytes = serde_json::to_vec(&self.nodes).expect("tree serialization failed");
+        Sha256::digest(bytes).into()
+    }
+
+    fn blank_direct_path(&mut self, leaf_index: u32) {
+        for node in direct_path(2 * leaf_index, self.leaf_count()) {
+            self.nodes[node as usize] = None;
+        }
+    }
+}
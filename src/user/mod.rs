@@ -1,21 +1,57 @@
 pub mod public_info;
+pub mod resend_cache;
 
+use chrono::{DateTime, Utc};
+use ed25519_dalek::Signer;
+use rand_core::{CryptoRng, OsRng, RngCore};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Display;
 
 use crate::keys::{
-    encrypted_message::EncryptedMessage, identity::IdentityKey,
-    one_time_prekey::OneTimePreKeyGroup, ratchet_key::RatchetKey, signed_prekey::SignedPreKey,
+    encrypted_message::EncryptedMessage,
+    identity::IdentityKey,
+    kem_prekey::KemPreKey,
+    one_time_prekey::OneTimePreKeyGroup,
+    ratchet_key::RatchetKey,
+    retry_request::{RetryRequest, message_hash},
+    sealed_sender_message::SealedSenderMessage,
+    sender_key_message::SenderKeyMessage,
+    signed_prekey::SignedPreKey,
 };
 use crate::{
-    crypto_utils::hkdf::derive_root_key,
-    double_ratchet::state::RatchetState,
+    content::{
+        Content, ContentMessage, DeliveryState, ExpiringMessage, ReceiptKind, ReceivedContent,
+        SentMessage,
+    },
+    crypto_utils::{cipher_suite::CipherSuiteId, rng::random_uuid},
+    double_ratchet::{
+        config::{SessionConfig, SkippedKeyPolicy},
+        session_info::SessionInfo,
+        state::RatchetState,
+    },
+    error::DecryptError,
+    group::{
+        membership::{
+            Group, GroupChange, GroupControlMessage, GroupControlResult, GroupMember, GroupRole,
+        },
+        sender_key::{SenderKeyRecord, SenderKeyState},
+    },
     keys::ephemeral_key::EphemeralKey,
-    user::public_info::UserPublicInfo,
+    sealed_sender::{
+        certificate::SenderCertificate,
+        envelope::{SenderIdentity, seal_message, unseal_message},
+    },
+    user::{public_info::UserPublicInfo, resend_cache::ResendCache},
     x3dh::session::{create_session_key, receive_session_key},
 };
 
+/// Number of archived sessions kept per peer.
+const MAX_ARCHIVED_SESSIONS: usize = 5;
+
+/// Number of base keys of accepted prekey messages remembered to recognize replays.
+const MAX_ACCEPTED_BASE_KEYS: usize = 1000;
+
 /// Represents a user in the Signal messaging protocol, with cryptographic identity, key material,
 /// and session state management.
 ///
@@ -25,7 +61,25 @@ use crate::{
 /// - `ik`: The user's long-term identity key.
 /// - `spk`: A signed pre-key used in X3DH session establishment.
 /// - `opk`: A pool of one-time pre-keys providing forward secrecy.
+/// - `kem_spk`: Signed last-resort ML-KEM-768 pre-key used by PQXDH.
+/// - `kem_opk`: A pool of signed one-time ML-KEM-768 pre-keys used by PQXDH.
 /// - `sessions`: A mapping from remote user IDs to ratchet session state.
+/// - `archived_sessions`: Previous sessions per remote user ID, most recent first, kept to
+///   decrypt messages still in flight when a session is replaced.
+/// - `sender_keys`: Our own sender key per group ID.
+/// - `received_sender_keys`: Other members' sender keys, indexed by `(group_id, sender_id)`.
+/// - `groups`: Groups we are a member of, indexed by group ID.
+/// - `session_config`: Options applied to the sessions we initiate.
+/// - `skipped_key_policy`: Retention policy of skipped message keys in all our sessions.
+/// - `accepted_base_keys`: Base keys of the prekey messages that started our most recent
+///   responder sessions, oldest first, to recognize replays once those sessions are gone.
+/// - `sent_messages`: Messages we sent with [`User::send_content`], indexed by message ID,
+///   with their delivery state.
+/// - `expiration_timers`: Disappearing-message timer in seconds per remote user ID, for
+///   conversations that have one.
+/// - `expiry_schedule`: Disappearing messages, sent or received, ordered by expiry.
+/// - `resend_cache`: Plaintexts of our most recent pairwise messages, kept to answer
+///   retry requests.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: String,
@@ -33,16 +87,45 @@ pub struct User {
     ik: IdentityKey,
     spk: SignedPreKey,
     opk: OneTimePreKeyGroup,
+    kem_spk: KemPreKey,
+    kem_opk: Vec<KemPreKey>,
     sessions: HashMap<String, RatchetState>,
+    archived_sessions: HashMap<String, Vec<RatchetState>>,
+    sender_keys: HashMap<String, SenderKeyState>,
+    received_sender_keys: HashMap<(String, String), SenderKeyRecord>,
+    groups: HashMap<String, Group>,
+    session_config: SessionConfig,
+    skipped_key_policy: SkippedKeyPolicy,
+    accepted_base_keys: VecDeque<[u8; 32]>,
+    sent_messages: HashMap<String, SentMessage>,
+    expiration_timers: HashMap<String, u32>,
+    expiry_schedule: Vec<ExpiringMessage>,
+    resend_cache: ResendCache,
 }
 
 impl User {
     /// Initializes a new user with a fresh identity key, signed pre-key, and a batch of one-time pre-keys.
     pub fn new(name: String) -> Self {
-        let id = uuid::Uuid::new_v4().to_string();
-        let ik = IdentityKey::new();
-        let spk = SignedPreKey::new(&ik.signing_key());
-        let opk = OneTimePreKeyGroup::new(100);
+        Self::new_with_rng(name, &mut OsRng)
+    }
+
+    /// Initializes a new user whose ID and key material are drawn from the given RNG.
+    ///
+    /// Together with [`User::send_message_with_rng`], a seeded RNG yields byte-identical
+    /// transcripts, for reproducible tests and test vectors.
+    ///
+    /// # Arguments
+    /// - `name`: Human-readable identifier of the user.
+    /// - `rng`: Source of randomness.
+    pub fn new_with_rng<R: CryptoRng + RngCore>(name: String, rng: &mut R) -> Self {
+        let id = random_uuid(rng);
+        let ik = IdentityKey::new_with_rng(rng);
+        let spk = SignedPreKey::new(&ik.signing_key(), rng);
+        let opk = OneTimePreKeyGroup::new(100, rng);
+        let kem_spk = KemPreKey::new(&ik.signing_key(), true, rng);
+        let kem_opk = (0..10)
+            .map(|_| KemPreKey::new(&ik.signing_key(), false, rng))
+            .collect();
 
         Self {
             id,
@@ -50,25 +133,71 @@ impl User {
             ik,
             spk,
             opk,
+            kem_spk,
+            kem_opk,
             sessions: HashMap::new(),
+            archived_sessions: HashMap::new(),
+            sender_keys: HashMap::new(),
+            received_sender_keys: HashMap::new(),
+            groups: HashMap::new(),
+            session_config: SessionConfig::default(),
+            skipped_key_policy: SkippedKeyPolicy::default(),
+            accepted_base_keys: VecDeque::new(),
+            sent_messages: HashMap::new(),
+            expiration_timers: HashMap::new(),
+            expiry_schedule: Vec::new(),
+            resend_cache: ResendCache::default(),
         }
     }
 
+    /// Sets the options applied to sessions we initiate from now on.
+    ///
+    /// Existing sessions keep the options they were created with; sessions initiated by
+    /// peers use the options chosen by the peer.
+    pub fn set_session_config(&mut self, config: SessionConfig) {
+        self.session_config = config;
+    }
+
+    /// Sets the retention policy applied by [`User::prune_skipped_keys`].
+    pub fn set_skipped_key_policy(&mut self, policy: SkippedKeyPolicy) {
+        self.skipped_key_policy = policy;
+    }
+
+    /// Purges the skipped message keys exceeding the retention policy from all current
+    /// and archived sessions. Meant to be called periodically.
+    ///
+    /// # Arguments
+    /// - `now`: Current time.
+    ///
+    /// # Returns
+    /// The number of purged keys.
+    pub fn prune_skipped_keys(&mut self, now: DateTime<Utc>) -> usize {
+        let policy = self.skipped_key_policy;
+        self.sessions
+            .values_mut()
+            .chain(self.archived_sessions.values_mut().flatten())
+            .map(|ratchet| ratchet.prune(now, &policy))
+            .sum()
+    }
+
     /// Returns the public-facing cryptographic material and metadata required for X3DH session establishment.
     pub fn public_info(&self) -> UserPublicInfo {
         UserPublicInfo {
             id: self.id.clone(),
             name: self.name.clone(),
             ik: self.ik.dh_public,
+            ik_sign: self.ik.signing_public(),
             spk: self.spk.public,
             opk: self.opk.public_group(),
+            kem_spk: self.kem_spk.public_key(),
+            kem_opk: self.kem_opk.iter().map(KemPreKey::public_key).collect(),
+            cipher_suites: CipherSuiteId::ALL.to_vec(),
         }
     }
 
-    /// Sends a message to the target user using their [`UserPublicInfo`].
+    /// Sends a text message to the target user using their [`UserPublicInfo`].
     ///
-    /// If no session exists, initializes a new one using the X3DH protocol, followed by
-    /// Double Ratchet encryption of the plaintext.
+    /// Thin wrapper around [`User::send_bytes`] for UTF-8 payloads.
     ///
     /// # Arguments
     /// - `to`: Public info of the recipient user.
@@ -77,19 +206,122 @@ impl User {
     /// # Returns
     /// An [`EncryptedMessage`] ready for transmission.
     pub fn send_message(&mut self, to: &UserPublicInfo, plaintext: &str) -> EncryptedMessage {
+        self.send_bytes(to, plaintext.as_bytes())
+    }
+
+    /// Sends a text message like [`User::send_message`], drawing randomness from `rng`.
+    ///
+    /// Thin wrapper around [`User::send_bytes_with_rng`].
+    pub fn send_message_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        to: &UserPublicInfo,
+        plaintext: &str,
+        rng: &mut R,
+    ) -> EncryptedMessage {
+        self.send_bytes_with_rng(to, plaintext.as_bytes(), rng)
+    }
+
+    /// Sends an arbitrary binary payload to the target user using their [`UserPublicInfo`].
+    ///
+    /// If no session exists, initializes a new one using the X3DH protocol, followed by
+    /// Double Ratchet encryption of the plaintext. When PQXDH is enabled in our session
+    /// config, the initiator also encapsulates to a KEM pre-key of the recipient; if the
+    /// bundle has no validly signed KEM pre-key, the session falls back to X3DH and the
+    /// message's `session_config` says so. Likewise, our preferred cipher suite is only
+    /// used if the recipient advertises it; otherwise the default suite is used.
+    ///
+    /// The recipient deletes the one-time pre-key a new session uses once it receives the
+    /// first message, so `to` must be a bundle fetched after any earlier session with the
+    /// recipient was established.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the recipient user.
+    /// - `plaintext`: Payload to encrypt (images, protobufs, ...).
+    ///
+    /// # Returns
+    /// An [`EncryptedMessage`] ready for transmission.
+    pub fn send_bytes(&mut self, to: &UserPublicInfo, plaintext: &[u8]) -> EncryptedMessage {
+        self.send_bytes_with_rng(to, plaintext, &mut OsRng)
+    }
+
+    /// Sends a binary payload like [`User::send_bytes`], drawing ephemeral keys, ratchet
+    /// keys, KEM encapsulations and header nonces from the given RNG.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the recipient user.
+    /// - `plaintext`: Payload to encrypt.
+    /// - `rng`: Source of randomness.
+    ///
+    /// # Returns
+    /// An [`EncryptedMessage`] ready for transmission.
+    pub fn send_bytes_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        to: &UserPublicInfo,
+        plaintext: &[u8],
+        rng: &mut R,
+    ) -> EncryptedMessage {
+        self.encrypt_for(to, plaintext, true, rng)
+    }
+
+    /// Encrypts a payload for `to`, establishing a session from its bundle if needed.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the recipient user.
+    /// - `plaintext`: Payload to encrypt.
+    /// - `use_opk`: Whether a new session uses one of the bundle's one-time pre-keys, X25519
+    ///   and ML-KEM. Group members start sessions from bundles cached in the group state,
+    ///   whose one-time pre-keys may already be deleted, so they opt out and encapsulate to
+    ///   the last-resort KEM pre-key.
+    /// - `rng`: Source of randomness.
+    ///
+    /// # Returns
+    /// An [`EncryptedMessage`] ready for transmission.
+    fn encrypt_for<R: CryptoRng + RngCore>(
+        &mut self,
+        to: &UserPublicInfo,
+        plaintext: &[u8],
+        use_opk: bool,
+        rng: &mut R,
+    ) -> EncryptedMessage {
         let receiver_id = to.id.clone();
 
         let mut used_opk: Option<[u8; 32]> = None;
         let mut used_ek: Option<[u8; 32]> = None;
+        let mut used_kem: Option<(String, Vec<u8>)> = None;
 
         let ratchet = self.sessions.entry(receiver_id.clone()).or_insert_with(|| {
-            let ek = EphemeralKey::new();
-            let opk = to.opk.use_key();
+            let ek = EphemeralKey::new_with_rng(rng);
+            let opk = use_opk.then(|| to.opk.use_key()).flatten();
             if let Some(ref opk_val) = opk {
                 used_opk = Some(opk_val.public);
             }
             used_ek = Some(ek.public);
 
+            let kem = self
+                .session_config
+                .pqxdh
+                .then(|| match use_opk {
+                    true => to.use_kem_prekey(),
+                    false => to.last_resort_kem_prekey(),
+                })
+                .flatten()
+                .and_then(|kem_pk| {
+                    let (ciphertext, shared_secret) = kem_pk.encapsulate(rng)?;
+                    Some((kem_pk.id, ciphertext, shared_secret))
+                });
+            let config = SessionConfig {
+                pqxdh: kem.is_some(),
+                cipher_suite: CipherSuiteId::negotiate(
+                    self.session_config.cipher_suite,
+                    &to.cipher_suites,
+                ),
+                ..self.session_config
+            };
+            let kem_shared_secret = kem.map(|(id, ciphertext, shared_secret)| {
+                used_kem = Some((id, ciphertext));
+                shared_secret
+            });
+
             let session = create_session_key(
                 self.name.clone(),
                 to.name.clone(),
@@ -98,63 +330,1199 @@ impl User {
                 to.spk,
                 to.ik,
                 opk.as_ref(),
+                kem_shared_secret,
+                config.kdf_mode,
             );
 
-            let rk = derive_root_key(&session.get_bytes());
-            let dhs = RatchetKey::new();
+            let dhs = RatchetKey::new(rng);
 
-            RatchetState::new(rk, dhs, Some(to.spk), true)
+            RatchetState::new(
+                &session,
+                dhs,
+                Some(to.spk),
+                true,
+                config,
+                (self.ik.dh_public, to.ik),
+            )
+            .with_base_key(ek.public)
         });
 
-        ratchet.encrypt(
+        let mut msg = ratchet.encrypt(
             plaintext,
             self.name.clone(),
             to.name.clone(),
             used_opk,
             used_ek,
-        )
+            rng,
+        );
+        if let Some((id, ciphertext)) = used_kem {
+            msg.kem_prekey_id = Some(id);
+            msg.kem_ciphertext = Some(ciphertext);
+        }
+        self.resend_cache
+            .insert(&receiver_id, message_hash(&msg.ciphertext), plaintext);
+        msg
     }
 
-    /// Receives and decrypts a message from another user using their [`UserPublicInfo`].
+    /// Receives and decrypts a text message from another user using their [`UserPublicInfo`].
     ///
-    /// If no session exists, attempts to reconstruct it using the sender's identity key,
-    /// the local one-time pre-key, and the ephemeral key used in the message.
+    /// Thin wrapper around [`User::receive_bytes`] for UTF-8 payloads.
     ///
     /// # Arguments
     /// - `from`: Sender's public key bundle.
     /// - `msg`: The [`EncryptedMessage`] to be decrypted.
     ///
     /// # Returns
-    /// The decrypted plaintext message, or `None` if decryption fails.
+    /// The decrypted plaintext message, or `None` if decryption fails or the payload is
+    /// not valid UTF-8.
     pub fn receive_message(
         &mut self,
         from: &UserPublicInfo,
         msg: &EncryptedMessage,
     ) -> Option<String> {
-        let sender_id = from.id.clone();
+        String::from_utf8(self.receive_bytes(from, msg)?).ok()
+    }
 
-        let ratchet = self.sessions.entry(sender_id.clone()).or_insert_with(|| {
-            let opk = msg
-                .opk_used
-                .and_then(|opk| self.opk.get_by_public_key(opk))
-                .unwrap();
+    /// Receives and decrypts a binary payload from another user using their [`UserPublicInfo`].
+    ///
+    /// If no session exists, attempts to reconstruct it using the sender's identity key,
+    /// the local one-time pre-key, and the ephemeral key used in the message.
+    ///
+    /// # Arguments
+    /// - `from`: Sender's public key bundle.
+    /// - `msg`: The [`EncryptedMessage`] to be decrypted.
+    ///
+    /// # Returns
+    /// The decrypted payload, or `None` if decryption fails.
+    pub fn receive_bytes(
+        &mut self,
+        from: &UserPublicInfo,
+        msg: &EncryptedMessage,
+    ) -> Option<Vec<u8>> {
+        self.try_receive_bytes(from, msg).ok()
+    }
 
-            let ek = msg.ek_used.unwrap_or([0u8; 32]);
-            let session = receive_session_key(
-                self.name.clone(),
-                from.name.clone(),
-                &self.ik,
-                &self.spk,
-                opk,
-                from.ik,
-                ek,
+    /// Receives and decrypts a binary payload like [`User::receive_bytes`], reporting why
+    /// decryption failed.
+    ///
+    /// Duplicate deliveries and replayed prekey messages are reported as
+    /// [`DecryptError::DuplicateMessage`] and leave the session untouched.
+    ///
+    /// # Arguments
+    /// - `from`: Sender's public key bundle.
+    /// - `msg`: The [`EncryptedMessage`] to be decrypted.
+    ///
+    /// # Returns
+    /// The decrypted payload, or the [`DecryptError`] that prevented decryption.
+    pub fn try_receive_bytes(
+        &mut self,
+        from: &UserPublicInfo,
+        msg: &EncryptedMessage,
+    ) -> Result<Vec<u8>, DecryptError> {
+        self.receive_from(&from.id, &from.name, from.ik, msg, |_| true)
+            .map(|(plaintext, _)| plaintext)
+    }
+
+    /// Sends a typed [`Content`] to the target user.
+    ///
+    /// The content is wrapped in a [`ContentMessage`] with a fresh ID, the current timestamp
+    /// and the conversation's expiry timer, then sent with [`User::send_bytes`]. Unless the
+    /// content is a receipt, a typing indicator or a session end, the message is tracked in
+    /// the [`DeliveryState::Sent`] state until receipts come back, and added to the expiry
+    /// schedule if the conversation has disappearing messages.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the recipient user.
+    /// - `content`: Content to send.
+    ///
+    /// # Returns
+    /// The message ID and the [`EncryptedMessage`] ready for transmission.
+    pub fn send_content(
+        &mut self,
+        to: &UserPublicInfo,
+        content: Content,
+    ) -> (String, EncryptedMessage) {
+        self.send_content_with_rng(to, content, &mut OsRng)
+    }
+
+    /// Sends a typed [`Content`] to the target user, drawing the message ID and all
+    /// encryption randomness from the given RNG.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the recipient user.
+    /// - `content`: Content to send.
+    /// - `rng`: Source of randomness. A seeded RNG yields reproducible message IDs and
+    ///   ciphertexts.
+    ///
+    /// # Returns
+    /// The message ID and the [`EncryptedMessage`] ready for transmission.
+    pub fn send_content_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        to: &UserPublicInfo,
+        content: Content,
+        rng: &mut R,
+    ) -> (String, EncryptedMessage) {
+        if let Content::ExpirationTimerUpdate { seconds } = content {
+            self.set_local_expiration_timer(&to.id, seconds);
+        }
+        let message = ContentMessage::new_with_rng(content, self.expiration_timer(&to.id), rng);
+        if message.content.expects_receipt() {
+            if let Some(expires_at) = message.expires_at() {
+                self.schedule_expiry(&to.id, &message.id, expires_at);
+            }
+            self.sent_messages.insert(
+                message.id.clone(),
+                SentMessage {
+                    recipient_id: to.id.clone(),
+                    timestamp: message.timestamp,
+                    state: DeliveryState::Sent,
+                },
             );
-            let rk = derive_root_key(&session.get_bytes());
-            let dhs = RatchetKey::from_keys(self.spk.get_private(), self.spk.public);
-            RatchetState::new(rk, dhs, None, false)
+        }
+        let msg = self.send_bytes_with_rng(to, &message.encode(), rng);
+        (message.id, msg)
+    }
+
+    /// Sends a delivery or read receipt for messages received from `to`.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the sender of the acknowledged messages.
+    /// - `kind`: Whether the messages were delivered or read.
+    /// - `message_ids`: IDs of the acknowledged messages, as found in [`ReceivedContent`].
+    ///
+    /// # Returns
+    /// An [`EncryptedMessage`] carrying the receipt.
+    pub fn send_receipt(
+        &mut self,
+        to: &UserPublicInfo,
+        kind: ReceiptKind,
+        message_ids: Vec<String>,
+    ) -> EncryptedMessage {
+        self.send_content(to, Content::Receipt { kind, message_ids })
+            .1
+    }
+
+    /// Receives, decrypts and decodes a [`Content`] sent with [`User::send_content`].
+    ///
+    /// Receipts update the delivery state of the acknowledged messages, provided they were
+    /// sent to the peer issuing the receipt. [`Content::EndSession`] archives the session
+    /// with the sender, unless it was decrypted by an archived session (a delayed end of a
+    /// session already replaced), and [`Content::ExpirationTimerUpdate`] adopts the sender's timer for
+    /// the conversation. Disappearing messages are added to the expiry schedule at the
+    /// expiry they carry, so both parties purge them at the same time.
+    ///
+    /// # Arguments
+    /// - `from`: Sender's public key bundle.
+    /// - `msg`: The [`EncryptedMessage`] to be decrypted.
+    ///
+    /// A message that decrypts but is not a [`ContentMessage`], such as a group control
+    /// message, is not consumed and can still be received with the matching API.
+    ///
+    /// # Returns
+    /// The decoded content along with its sender, or `None` if decryption or decoding fails.
+    pub fn receive_content(
+        &mut self,
+        from: &UserPublicInfo,
+        msg: &EncryptedMessage,
+    ) -> Option<ReceivedContent> {
+        let (plaintext, current) = self
+            .receive_from(&from.id, &from.name, from.ik, msg, |payload| {
+                ContentMessage::decode(payload).is_some()
+            })
+            .ok()?;
+        let message = ContentMessage::decode(&plaintext)?;
+
+        if let Content::Receipt { kind, message_ids } = &message.content {
+            let state = match kind {
+                ReceiptKind::Delivery => DeliveryState::Delivered,
+                ReceiptKind::Read => DeliveryState::Read,
+            };
+            for id in message_ids {
+                if let Some(sent) = self.sent_messages.get_mut(id)
+                    && sent.recipient_id == from.id
+                {
+                    sent.state = sent.state.max(state);
+                }
+            }
+        }
+
+        match message.content {
+            Content::EndSession if current => {
+                self.reset_session(&from.id);
+            }
+            Content::ExpirationTimerUpdate { seconds } => {
+                self.set_local_expiration_timer(&from.id, seconds);
+            }
+            _ => {}
+        }
+        let expires_at = message
+            .content
+            .expects_receipt()
+            .then(|| message.expires_at())
+            .flatten();
+        if let Some(expires_at) = expires_at {
+            self.schedule_expiry(&from.id, &message.id, expires_at);
+        }
+
+        Some(ReceivedContent {
+            sender_id: from.id.clone(),
+            sender_name: from.name.clone(),
+            sender_identity_key: from.ik,
+            message_id: message.id,
+            timestamp: message.timestamp,
+            expires_at,
+            content: message.content,
+        })
+    }
+
+    /// Sets the disappearing-message timer of the conversation with `to` and synchronizes
+    /// it with the peer.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the peer.
+    /// - `seconds`: Lifetime of new messages in seconds, `0` to disable.
+    ///
+    /// # Returns
+    /// The [`EncryptedMessage`] carrying the [`Content::ExpirationTimerUpdate`].
+    pub fn set_expiration_timer(&mut self, to: &UserPublicInfo, seconds: u32) -> EncryptedMessage {
+        self.send_content(to, Content::ExpirationTimerUpdate { seconds })
+            .1
+    }
+
+    /// Returns the disappearing-message timer of the conversation with `peer_id`, in
+    /// seconds, or `0` if messages do not expire.
+    pub fn expiration_timer(&self, peer_id: &str) -> u32 {
+        self.expiration_timers.get(peer_id).copied().unwrap_or(0)
+    }
+
+    /// Returns the disappearing messages, sent or received, ordered by expiry.
+    pub fn expiry_schedule(&self) -> &[ExpiringMessage] {
+        &self.expiry_schedule
+    }
+
+    /// Removes and returns the disappearing messages expired at `now`, so that the message
+    /// store can purge them.
+    pub fn take_expired(&mut self, now: DateTime<Utc>) -> Vec<ExpiringMessage> {
+        let due = self
+            .expiry_schedule
+            .partition_point(|message| message.expires_at <= now);
+        self.expiry_schedule.drain(..due).collect()
+    }
+
+    /// Ends the session with `to`.
+    ///
+    /// A [`Content::EndSession`] message is encrypted in the current session, which is then
+    /// archived. The peer archives its side when it receives the message, and the next
+    /// message in either direction establishes a new session.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the peer.
+    ///
+    /// # Returns
+    /// The [`EncryptedMessage`] to deliver to the peer.
+    pub fn end_session(&mut self, to: &UserPublicInfo) -> EncryptedMessage {
+        let (_, msg) = self.send_content(to, Content::EndSession);
+        self.reset_session(&to.id);
+        msg
+    }
+
+    /// Archives the current session with `peer_id`, without notifying the peer.
+    ///
+    /// The next message sent to the peer runs a fresh X3DH (or PQXDH) handshake with the
+    /// bundle given to that send. Messages still in flight in the old session can still be
+    /// decrypted from the archive.
+    ///
+    /// # Returns
+    /// `true` if a session was archived.
+    pub fn reset_session(&mut self, peer_id: &str) -> bool {
+        let Some(ratchet) = self.sessions.remove(peer_id) else {
+            return false;
+        };
+        self.archive_session(peer_id, ratchet);
+        true
+    }
+
+    /// Returns a secret-free summary of the current session with `peer_id`.
+    ///
+    /// # Returns
+    /// The [`SessionInfo`], or `None` if no session exists with the peer.
+    pub fn session_info(&self, peer_id: &str) -> Option<SessionInfo> {
+        self.sessions.get(peer_id).map(RatchetState::info)
+    }
+
+    /// Returns the delivery state of a message sent with [`User::send_content`].
+    ///
+    /// # Returns
+    /// The latest reported [`DeliveryState`], or `None` if the message is unknown.
+    pub fn delivery_state(&self, message_id: &str) -> Option<DeliveryState> {
+        self.sent_messages.get(message_id).map(|sent| sent.state)
+    }
+
+    /// Builds a signed request asking the sender of an undecryptable message to resend it.
+    ///
+    /// # Arguments
+    /// - `from`: Public info of the sender of the failed message.
+    /// - `msg`: The [`EncryptedMessage`] that could not be decrypted.
+    ///
+    /// # Returns
+    /// A [`RetryRequest`] to deliver to the sender outside of the pairwise session.
+    pub fn create_retry_request(
+        &self,
+        from: &UserPublicInfo,
+        msg: &EncryptedMessage,
+    ) -> RetryRequest {
+        let mut request = RetryRequest {
+            requester_id: self.id.clone(),
+            sender_id: from.id.clone(),
+            message_hash: message_hash(&msg.ciphertext),
+            timestamp: Utc::now(),
+            signature: Vec::new(),
+        };
+        request.signature = self.ik.signing_key().sign(&request.signed_bytes()).to_vec();
+        request
+    }
+
+    /// Answers a retry request by re-encrypting the requested message.
+    ///
+    /// The current session with the requester is archived first, so the message is sent
+    /// in a freshly established session that the requester can decrypt even if its side of
+    /// the old session is lost or corrupted.
+    ///
+    /// Requests issued more than a day before `now`, or dated more than a few minutes
+    /// after it, are ignored so that a captured request cannot be replayed later to force
+    /// a session reset.
+    ///
+    /// # Arguments
+    /// - `from`: Public info of the requester.
+    /// - `request`: The [`RetryRequest`] received from `from`.
+    /// - `now`: Current time.
+    ///
+    /// # Returns
+    /// The re-encrypted [`EncryptedMessage`], or `None` if the request is not addressed to
+    /// us, is not signed by `from`, is too old or dated in the future, or the message is no
+    /// longer in the resend cache.
+    pub fn handle_retry_request(
+        &mut self,
+        from: &UserPublicInfo,
+        request: &RetryRequest,
+        now: DateTime<Utc>,
+    ) -> Option<EncryptedMessage> {
+        if request.requester_id != from.id
+            || request.sender_id != self.id
+            || !request.verify(&from.ik_sign)
+            || !request.is_fresh(now)
+        {
+            return None;
+        }
+        let plaintext = self.resend_cache.take(&from.id, &request.message_hash)?;
+
+        self.reset_session(&from.id);
+        Some(self.send_bytes(from, &plaintext))
+    }
+
+    /// Sends a text message whose sender is hidden from the relay (sealed sender).
+    ///
+    /// Thin wrapper around [`User::send_sealed_bytes`] for UTF-8 payloads.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the recipient user.
+    /// - `plaintext`: Message content to encrypt.
+    /// - `certificate`: Our sender certificate, issued by the server for our identity key.
+    ///
+    /// # Returns
+    /// A [`SealedSenderMessage`] whose only visible metadata is the recipient ID.
+    pub fn send_sealed_message(
+        &mut self,
+        to: &UserPublicInfo,
+        plaintext: &str,
+        certificate: &SenderCertificate,
+    ) -> SealedSenderMessage {
+        self.send_sealed_bytes(to, plaintext.as_bytes(), certificate)
+    }
+
+    /// Sends a binary payload whose sender is hidden from the relay (sealed sender).
+    ///
+    /// The payload is encrypted as with [`User::send_bytes`], then wrapped together
+    /// with our sender certificate so that only the recipient's identity key can reveal
+    /// who sent it.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the recipient user.
+    /// - `plaintext`: Payload to encrypt.
+    /// - `certificate`: Our sender certificate, issued by the server for our identity key.
+    ///
+    /// # Returns
+    /// A [`SealedSenderMessage`] whose only visible metadata is the recipient ID.
+    pub fn send_sealed_bytes(
+        &mut self,
+        to: &UserPublicInfo,
+        plaintext: &[u8],
+        certificate: &SenderCertificate,
+    ) -> SealedSenderMessage {
+        let msg = self.send_bytes(to, plaintext);
+        seal_message(
+            &self.ik,
+            certificate.clone(),
+            &self.name,
+            &to.id,
+            &to.ik,
+            msg,
+        )
+    }
+
+    /// Unseals and decrypts a sealed-sender text message addressed to us.
+    ///
+    /// Thin wrapper around [`User::receive_sealed_bytes`] for UTF-8 payloads.
+    ///
+    /// # Arguments
+    /// - `msg`: The [`SealedSenderMessage`] to open.
+    /// - `trust_root`: Ed25519 public key of the trust root.
+    ///
+    /// # Returns
+    /// The sender identity and the decrypted plaintext, or `None` if opening fails or the
+    /// payload is not valid UTF-8.
+    pub fn receive_sealed_message(
+        &mut self,
+        msg: &SealedSenderMessage,
+        trust_root: &[u8; 32],
+    ) -> Option<(SenderIdentity, String)> {
+        let (sender, plaintext) = self.receive_sealed_bytes(msg, trust_root)?;
+        Some((sender, String::from_utf8(plaintext).ok()?))
+    }
+
+    /// Unseals and decrypts a sealed-sender binary payload addressed to us.
+    ///
+    /// The sender certificate must chain to `trust_root` and be unexpired, and it must
+    /// certify the identity key that authenticated the inner layer; this binds the
+    /// sender's user ID to the key that encrypted the message.
+    ///
+    /// # Arguments
+    /// - `msg`: The [`SealedSenderMessage`] to open.
+    /// - `trust_root`: Ed25519 public key of the trust root.
+    ///
+    /// # Returns
+    /// The sender identity and the decrypted payload, or `None` if the message is not for
+    /// us, carries an invalid certificate, or fails to unseal or decrypt.
+    pub fn receive_sealed_bytes(
+        &mut self,
+        msg: &SealedSenderMessage,
+        trust_root: &[u8; 32],
+    ) -> Option<(SenderIdentity, Vec<u8>)> {
+        if msg.receiver != self.id {
+            return None;
+        }
+        let (sender, inner) = unseal_message(&self.ik, trust_root, Utc::now(), msg)?;
+        if inner.sender != sender.name {
+            return None;
+        }
+
+        let (plaintext, _) = self
+            .receive_from(
+                &sender.id,
+                &sender.name,
+                sender.identity_key,
+                &inner,
+                |_| true,
+            )
+            .ok()?;
+        Some((sender, plaintext))
+    }
+
+    /// Creates a new group with ourselves as its only admin.
+    ///
+    /// Every other member receives the full group state together with our sender key.
+    ///
+    /// # Arguments
+    /// - `name`: Human-readable group name.
+    /// - `members`: Public info of the initial members (ourselves excluded).
+    ///
+    /// # Returns
+    /// The new group ID and the pairwise messages to deliver, as `(recipient_id, message)` pairs.
+    pub fn create_group(
+        &mut self,
+        name: &str,
+        members: &[UserPublicInfo],
+    ) -> (String, Vec<(String, EncryptedMessage)>) {
+        self.create_group_with_rng(name, members, &mut OsRng)
+    }
+
+    /// Creates a new group, drawing its ID, our sender key and the pairwise messages from
+    /// the given RNG.
+    ///
+    /// # Arguments
+    /// - `name`: Human-readable group name.
+    /// - `members`: Public info of the initial members (ourselves excluded).
+    /// - `rng`: Source of randomness. A seeded RNG yields a reproducible group.
+    ///
+    /// # Returns
+    /// The new group ID and the pairwise messages to deliver, as `(recipient_id, message)` pairs.
+    pub fn create_group_with_rng<R: CryptoRng + RngCore>(
+        &mut self,
+        name: &str,
+        members: &[UserPublicInfo],
+        rng: &mut R,
+    ) -> (String, Vec<(String, EncryptedMessage)>) {
+        let group_id = random_uuid(rng);
+
+        let mut group_members = vec![GroupMember {
+            info: self.public_info(),
+            role: GroupRole::Admin,
+        }];
+        group_members.extend(
+            members
+                .iter()
+                .filter(|info| info.id != self.id)
+                .map(|info| GroupMember {
+                    info: info.clone(),
+                    role: GroupRole::Member,
+                }),
+        );
+        let group = Group::new(group_id.clone(), name.to_string(), group_members);
+        let change = GroupChange::Create {
+            name: group.name.clone(),
+            members: group.members().to_vec(),
+        };
+        let recipients = self.other_members(&group);
+
+        self.groups.insert(group_id.clone(), group);
+        self.sender_keys
+            .insert(group_id.clone(), SenderKeyState::new_with_rng(rng));
+
+        let outgoing = recipients
+            .iter()
+            .map(|info| {
+                let msg = self.send_group_control(info, &group_id, Some(change.clone()), true, rng);
+                (info.id.clone(), msg)
+            })
+            .collect();
+
+        (group_id, outgoing)
+    }
+
+    /// Adds a member to a group we administer.
+    ///
+    /// Existing members are notified of the addition, and the new member receives the
+    /// full group state together with our sender key.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the group.
+    /// - `member`: Public info of the member to add.
+    /// - `role`: Role granted to the new member.
+    ///
+    /// # Returns
+    /// The pairwise messages to deliver, or `None` if the group is unknown or we are not an admin.
+    pub fn add_group_member(
+        &mut self,
+        group_id: &str,
+        member: &UserPublicInfo,
+        role: GroupRole,
+    ) -> Option<Vec<(String, EncryptedMessage)>> {
+        let group = self.groups.get(group_id)?;
+        if !group.is_admin(&self.id) || member.id == self.id {
+            return None;
+        }
+        let new_member = GroupMember {
+            info: member.clone(),
+            role,
+        };
+        let existing: Vec<UserPublicInfo> = self
+            .other_members(group)
+            .into_iter()
+            .filter(|info| info.id != member.id)
+            .collect();
+
+        let group = self.groups.get_mut(group_id)?;
+        group.add_member(new_member.clone());
+        let snapshot = GroupChange::Create {
+            name: group.name.clone(),
+            members: group.members().to_vec(),
+        };
+
+        let mut outgoing = Vec::new();
+        for info in existing {
+            let change = GroupChange::Add {
+                member: Box::new(new_member.clone()),
+            };
+            let msg = self.send_group_control(&info, group_id, Some(change), false, &mut OsRng);
+            outgoing.push((info.id.clone(), msg));
+        }
+        let msg = self.send_group_control(member, group_id, Some(snapshot), true, &mut OsRng);
+        outgoing.push((member.id.clone(), msg));
+
+        Some(outgoing)
+    }
+
+    /// Removes a member from a group we administer.
+    ///
+    /// Our sender key is rotated and the new key is sent only to the remaining members,
+    /// so the removed member cannot read future group messages.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the group.
+    /// - `member_id`: User ID of the member to remove.
+    ///
+    /// # Returns
+    /// The pairwise messages to deliver, or `None` if the group or member is unknown or we
+    /// are not an admin.
+    pub fn remove_group_member(
+        &mut self,
+        group_id: &str,
+        member_id: &str,
+    ) -> Option<Vec<(String, EncryptedMessage)>> {
+        let group = self.groups.get_mut(group_id)?;
+        if !group.is_admin(&self.id) || member_id == self.id {
+            return None;
+        }
+        let removed = group.member(member_id)?.info.clone();
+        group.remove_member(member_id);
+
+        let change = GroupChange::Remove {
+            member_id: member_id.to_string(),
+        };
+        let mut outgoing = self.rotate_sender_key(group_id, member_id, Some(&change));
+        let msg = self.send_group_control(&removed, group_id, Some(change), false, &mut OsRng);
+        outgoing.push((removed.id.clone(), msg));
+
+        Some(outgoing)
+    }
+
+    /// Leaves a group, notifying the other members and discarding all group key material.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the group.
+    ///
+    /// # Returns
+    /// The pairwise messages to deliver, or `None` if the group is unknown.
+    pub fn leave_group(&mut self, group_id: &str) -> Option<Vec<(String, EncryptedMessage)>> {
+        let group = self.groups.remove(group_id)?;
+        self.forget_group_keys(group_id);
+
+        let change = GroupChange::Leave {
+            member_id: self.id.clone(),
+        };
+        let outgoing = self
+            .other_members(&group)
+            .iter()
+            .map(|info| {
+                let msg = self.send_group_control(
+                    info,
+                    group_id,
+                    Some(change.clone()),
+                    false,
+                    &mut OsRng,
+                );
+                (info.id.clone(), msg)
+            })
+            .collect();
+
+        Some(outgoing)
+    }
+
+    /// Returns the local view of a group, if we are a member.
+    pub fn group(&self, group_id: &str) -> Option<&Group> {
+        self.groups.get(group_id)
+    }
+
+    /// Sends our sender key for `group_id` to another member over the pairwise session.
+    ///
+    /// A sender key is generated for the group on first use.
+    ///
+    /// # Arguments
+    /// - `to`: Public info of the group member.
+    /// - `group_id`: Identifier of the group.
+    ///
+    /// # Returns
+    /// An [`EncryptedMessage`] carrying a [`GroupControlMessage`] with our sender key.
+    pub fn send_sender_key(&mut self, to: &UserPublicInfo, group_id: &str) -> EncryptedMessage {
+        self.send_group_control(to, group_id, None, true, &mut OsRng)
+    }
+
+    /// Receives another member's sender key over the pairwise session.
+    ///
+    /// Thin wrapper around [`User::receive_group_control`] for messages produced by
+    /// [`User::send_sender_key`]. Any previously stored key for the same sender and group
+    /// is replaced.
+    ///
+    /// # Arguments
+    /// - `from`: Sender's public key bundle.
+    /// - `msg`: The [`EncryptedMessage`] produced by [`User::send_sender_key`].
+    ///
+    /// # Returns
+    /// The group ID the sender key belongs to, or `None` if decryption or parsing fails or
+    /// the sender is not a member of a group we know.
+    pub fn receive_sender_key(
+        &mut self,
+        from: &UserPublicInfo,
+        msg: &EncryptedMessage,
+    ) -> Option<String> {
+        let group_id = self.receive_group_control(from, msg)?.group_id;
+        self.groups.get(&group_id)?.member(&from.id)?;
+        Some(group_id)
+    }
+
+    /// Receives and applies a group control message sent over the pairwise session.
+    ///
+    /// Membership changes are only accepted from admins (or, for [`GroupChange::Leave`],
+    /// from the leaving member), and removals only for current members. When another
+    /// member is removed or leaves, our own sender key is rotated and redistributed to the
+    /// remaining members.
+    ///
+    /// An attached sender key is only stored if the sender is a member of a group we know,
+    /// either already or through the [`GroupChange::Create`] carried by the same message.
+    ///
+    /// A message that decrypts but is not a [`GroupControlMessage`], such as a
+    /// [`ContentMessage`], is not consumed and can still be received with the matching API.
+    ///
+    /// # Arguments
+    /// - `from`: Sender's public key bundle.
+    /// - `msg`: The [`EncryptedMessage`] carrying the [`GroupControlMessage`].
+    ///
+    /// # Returns
+    /// A [`GroupControlResult`] with the applied change and any replies to deliver, or
+    /// `None` if decryption or parsing fails or the change is not authorized.
+    pub fn receive_group_control(
+        &mut self,
+        from: &UserPublicInfo,
+        msg: &EncryptedMessage,
+    ) -> Option<GroupControlResult> {
+        let (payload, _) = self
+            .receive_from(&from.id, &from.name, from.ik, msg, |payload| {
+                GroupControlMessage::decode(payload).is_some()
+            })
+            .ok()?;
+        let control = GroupControlMessage::decode(&payload)?;
+        let group_id = control.group_id;
+
+        let replies = match &control.change {
+            Some(change) => self.apply_group_change(from, &group_id, change)?,
+            None => Vec::new(),
+        };
+
+        if let Some(skdm) = control.sender_key {
+            let is_member = self
+                .groups
+                .get(&group_id)
+                .is_some_and(|group| group.member(&from.id).is_some());
+            if skdm.group_id == group_id && is_member {
+                self.received_sender_keys.insert(
+                    (group_id.clone(), from.id.clone()),
+                    SenderKeyRecord::from_distribution(&skdm),
+                );
+            }
+        }
+
+        Some(GroupControlResult {
+            group_id,
+            change: control.change,
+            replies,
+        })
+    }
+
+    /// Encrypts a text message once for every member of a group.
+    ///
+    /// Thin wrapper around [`User::send_group_bytes`] for UTF-8 payloads.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the group.
+    /// - `plaintext`: Message content to encrypt.
+    ///
+    /// # Returns
+    /// A signed [`SenderKeyMessage`] that can be fanned out as-is to all members, or `None`
+    /// if we are not a member of the group.
+    pub fn send_group_message(
+        &mut self,
+        group_id: &str,
+        plaintext: &str,
+    ) -> Option<SenderKeyMessage> {
+        self.send_group_bytes(group_id, plaintext.as_bytes())
+    }
+
+    /// Encrypts a binary payload once for every member of a group.
+    ///
+    /// The sender key distributed when we created or joined the group is used; no key is
+    /// generated here, since members could not decrypt with a key they never received.
+    ///
+    /// # Arguments
+    /// - `group_id`: Identifier of the group.
+    /// - `plaintext`: Payload to encrypt.
+    ///
+    /// # Returns
+    /// A signed [`SenderKeyMessage`] that can be fanned out as-is to all members, or `None`
+    /// if we are not a member of the group or have not distributed a sender key for it.
+    pub fn send_group_bytes(
+        &mut self,
+        group_id: &str,
+        plaintext: &[u8],
+    ) -> Option<SenderKeyMessage> {
+        self.groups.get(group_id)?;
+        let sender_key = self.sender_keys.get_mut(group_id)?;
+        Some(sender_key.encrypt(group_id, self.name.clone(), plaintext))
+    }
+
+    /// Verifies and decrypts a group text message using the sender's distributed key.
+    ///
+    /// Thin wrapper around [`User::receive_group_bytes`] for UTF-8 payloads.
+    ///
+    /// # Arguments
+    /// - `from`: Sender's public key bundle.
+    /// - `msg`: The [`SenderKeyMessage`] to be decrypted.
+    ///
+    /// # Returns
+    /// The decrypted plaintext message, or `None` if no sender key is known, decryption
+    /// fails or the payload is not valid UTF-8.
+    pub fn receive_group_message(
+        &mut self,
+        from: &UserPublicInfo,
+        msg: &SenderKeyMessage,
+    ) -> Option<String> {
+        String::from_utf8(self.receive_group_bytes(from, msg)?).ok()
+    }
+
+    /// Verifies and decrypts a group binary payload using the sender's distributed key.
+    ///
+    /// # Arguments
+    /// - `from`: Sender's public key bundle.
+    /// - `msg`: The [`SenderKeyMessage`] to be decrypted.
+    ///
+    /// # Returns
+    /// The decrypted payload, or `None` if no sender key is known or decryption fails.
+    pub fn receive_group_bytes(
+        &mut self,
+        from: &UserPublicInfo,
+        msg: &SenderKeyMessage,
+    ) -> Option<Vec<u8>> {
+        let record = self
+            .received_sender_keys
+            .get_mut(&(msg.group_id.clone(), from.id.clone()))?;
+
+        record.decrypt(msg)
+    }
+
+    /// Decrypts a message from the given sender, running the responder side of X3DH
+    /// (or PQXDH) if the message starts a new session.
+    ///
+    /// Messages are tried against the current session, then against archived ones. A
+    /// prekey message whose base key matches no known session starts a new session, which
+    /// replaces the current one once the message decrypts. Until then the new session is
+    /// provisional: a message that fails to decrypt, such as garbage from an unknown
+    /// sender, leaves no session behind and records nothing, so it cannot block the real
+    /// first message. Once it decrypts, the one-time pre-keys it used are deleted. A prekey
+    /// message reusing the base key of a session we recently accepted, or a deleted
+    /// one-time pre-key, is a replay and never rebuilds it.
+    ///
+    /// The session that decrypts the message is only updated, and a new session only kept,
+    /// if `accept` recognizes the payload.
+    ///
+    /// # Returns
+    /// The decrypted payload and whether it was decrypted by the current session with the
+    /// sender (including one the message just started), or the [`DecryptError`] that
+    /// prevented decryption.
+    fn receive_from(
+        &mut self,
+        sender_id: &str,
+        sender_name: &str,
+        sender_ik: [u8; 32],
+        msg: &EncryptedMessage,
+        accept: impl Fn(&[u8]) -> bool,
+    ) -> Result<(Vec<u8>, bool), DecryptError> {
+        let base_key = msg.ek_used;
+        let in_session =
+            |ratchet: &RatchetState| base_key.is_none() || ratchet.base_key() == base_key;
+
+        let mut error = None;
+        let sessions = self
+            .sessions
+            .get_mut(sender_id)
+            .into_iter()
+            .map(|ratchet| (true, ratchet))
+            .chain(
+                self.archived_sessions
+                    .get_mut(sender_id)
+                    .into_iter()
+                    .flatten()
+                    .map(|ratchet| (false, ratchet)),
+            )
+            .filter(|(_, ratchet)| in_session(ratchet));
+        for (current, ratchet) in sessions {
+            match ratchet.decrypt(msg, &accept) {
+                Ok(plaintext) => return Ok((plaintext, current)),
+                Err(e @ (DecryptError::DuplicateMessage | DecryptError::UnexpectedPayload)) => {
+                    return Err(e);
+                }
+                Err(e) => error = Some(e),
+            }
+        }
+
+        let Some(base_key) = base_key else {
+            return Err(error.unwrap_or(DecryptError::NoSession));
+        };
+        let known = self.accepted_base_keys.contains(&base_key)
+            || self
+                .sessions
+                .get(sender_id)
+                .is_some_and(|ratchet| ratchet.base_key() == Some(base_key));
+        if known {
+            return Err(DecryptError::DuplicateMessage);
+        }
+
+        let mut ratchet = self
+            .responder_session(sender_name, sender_ik, msg)
+            .ok_or(DecryptError::InvalidPreKeyMessage)?;
+        let plaintext = ratchet.decrypt(msg, &accept)?;
+        if self.accepted_base_keys.len() == MAX_ACCEPTED_BASE_KEYS {
+            self.accepted_base_keys.pop_front();
+        }
+        self.accepted_base_keys.push_back(base_key);
+        if let Some(opk) = msg.opk_used {
+            self.opk.remove(opk);
+        }
+        if let Some(id) = &msg.kem_prekey_id {
+            self.kem_opk.retain(|key| key.id != *id);
+        }
+        if let Some(previous) = self.sessions.insert(sender_id.to_string(), ratchet) {
+            self.archive_session(sender_id, previous);
+        }
+        Ok((plaintext, true))
+    }
+
+    /// Builds the responder side of a session from a prekey message.
+    ///
+    /// The one-time pre-keys the message references are only looked up here. They are
+    /// deleted by [`User::receive_from`] once the message decrypts, so that a forged prekey
+    /// message cannot burn them. Messages starting a session without a one-time pre-key, as group
+    /// members do from cached bundles, are accepted too.
+    ///
+    /// # Returns
+    /// The new [`RatchetState`], or `None` if the message does not carry the X3DH fields,
+    /// references an unknown pre-key or carries a malformed KEM ciphertext.
+    fn responder_session(
+        &self,
+        sender_name: &str,
+        sender_ik: [u8; 32],
+        msg: &EncryptedMessage,
+    ) -> Option<RatchetState> {
+        let config = msg.session_config.unwrap_or_default();
+        let kem_shared_secret = match (&msg.kem_prekey_id, &msg.kem_ciphertext) {
+            (Some(id), Some(ciphertext)) if config.pqxdh => {
+                Some(self.kem_prekey(id)?.decapsulate(ciphertext)?)
+            }
+            (None, None) if !config.pqxdh => None,
+            _ => return None,
+        };
+        let opk = match msg.opk_used {
+            Some(public) => Some(self.opk.get_by_public_key(public)?),
+            None => None,
+        };
+        let ek = msg.ek_used?;
+
+        let session = receive_session_key(
+            self.name.clone(),
+            sender_name.to_string(),
+            &self.ik,
+            &self.spk,
+            opk.as_ref(),
+            sender_ik,
+            ek,
+            kem_shared_secret,
+            config.kdf_mode,
+        );
+        let dhs = RatchetKey::from_keys(self.spk.get_private(), self.spk.public);
+        Some(
+            RatchetState::new(
+                &session,
+                dhs,
+                None,
+                false,
+                config,
+                (self.ik.dh_public, sender_ik),
+            )
+            .with_base_key(ek),
+        )
+    }
+
+    /// Records the disappearing-message timer of the conversation with `peer_id`.
+    fn set_local_expiration_timer(&mut self, peer_id: &str, seconds: u32) {
+        if seconds == 0 {
+            self.expiration_timers.remove(peer_id);
+        } else {
+            self.expiration_timers.insert(peer_id.to_string(), seconds);
+        }
+    }
+
+    /// Inserts a message into the expiry schedule, keeping it ordered by expiry.
+    fn schedule_expiry(&mut self, peer_id: &str, message_id: &str, expires_at: DateTime<Utc>) {
+        let position = self
+            .expiry_schedule
+            .partition_point(|message| message.expires_at <= expires_at);
+        self.expiry_schedule.insert(
+            position,
+            ExpiringMessage {
+                peer_id: peer_id.to_string(),
+                message_id: message_id.to_string(),
+                expires_at,
+            },
+        );
+    }
+
+    /// Moves a session with `peer_id` to the archive, keeping at most
+    /// [`MAX_ARCHIVED_SESSIONS`] archived sessions per peer, most recent first.
+    fn archive_session(&mut self, peer_id: &str, ratchet: RatchetState) {
+        let archived = self
+            .archived_sessions
+            .entry(peer_id.to_string())
+            .or_default();
+        archived.insert(0, ratchet);
+        archived.truncate(MAX_ARCHIVED_SESSIONS);
+    }
+
+    /// Looks up one of our KEM pre-keys (one-time or last-resort) by ID.
+    fn kem_prekey(&self, id: &str) -> Option<&KemPreKey> {
+        self.kem_opk
+            .iter()
+            .chain(std::iter::once(&self.kem_spk))
+            .find(|key| key.id == id)
+    }
+
+    /// Applies a membership change received from `from` to our local group state.
+    ///
+    /// # Returns
+    /// The pairwise replies to deliver, or `None` if the change is not authorized.
+    fn apply_group_change(
+        &mut self,
+        from: &UserPublicInfo,
+        group_id: &str,
+        change: &GroupChange,
+    ) -> Option<Vec<(String, EncryptedMessage)>> {
+        match change {
+            GroupChange::Create { name, members } => {
+                let group = Group::new(group_id.to_string(), name.clone(), members.clone());
+                if !group.is_admin(&from.id) || group.member(&self.id).is_none() {
+                    return None;
+                }
+                if self
+                    .groups
+                    .get(group_id)
+                    .is_some_and(|existing| !existing.is_admin(&from.id))
+                {
+                    return None;
+                }
+                let recipients = self.other_members(&group);
+                self.groups.insert(group_id.to_string(), group);
+
+                Some(
+                    recipients
+                        .iter()
+                        .map(|info| {
+                            let msg =
+                                self.send_group_control(info, group_id, None, true, &mut OsRng);
+                            (info.id.clone(), msg)
+                        })
+                        .collect(),
+                )
+            }
+            GroupChange::Add { member } => {
+                let group = self.groups.get_mut(group_id)?;
+                if !group.is_admin(&from.id) {
+                    return None;
+                }
+                group.add_member(member.as_ref().clone());
+                if member.info.id == self.id {
+                    return Some(Vec::new());
+                }
+
+                let msg = self.send_group_control(&member.info, group_id, None, true, &mut OsRng);
+                Some(vec![(member.info.id.clone(), msg)])
+            }
+            GroupChange::Remove { member_id } | GroupChange::Leave { member_id } => {
+                let group = self.groups.get_mut(group_id)?;
+                let authorized = match change {
+                    GroupChange::Leave { .. } => *member_id == from.id,
+                    _ => group.is_admin(&from.id),
+                };
+                if !authorized || group.member(member_id).is_none() {
+                    return None;
+                }
+
+                if *member_id == self.id {
+                    self.groups.remove(group_id);
+                    self.forget_group_keys(group_id);
+                    return Some(Vec::new());
+                }
+
+                group.remove_member(member_id);
+                Some(self.rotate_sender_key(group_id, member_id, None))
+            }
+        }
+    }
+
+    /// Replaces our sender key for `group_id` after `departed_id` left the group and
+    /// distributes the new key, along with `change` if given, to every remaining member.
+    fn rotate_sender_key(
+        &mut self,
+        group_id: &str,
+        departed_id: &str,
+        change: Option<&GroupChange>,
+    ) -> Vec<(String, EncryptedMessage)> {
+        self.received_sender_keys
+            .remove(&(group_id.to_string(), departed_id.to_string()));
+        self.sender_keys
+            .insert(group_id.to_string(), SenderKeyState::new());
+
+        let recipients = match self.groups.get(group_id) {
+            Some(group) => self.other_members(group),
+            None => Vec::new(),
+        };
+        recipients
+            .iter()
+            .map(|info| {
+                let msg =
+                    self.send_group_control(info, group_id, change.cloned(), true, &mut OsRng);
+                (info.id.clone(), msg)
+            })
+            .collect()
+    }
+
+    /// Encrypts a [`GroupControlMessage`] for `to` over the pairwise session.
+    ///
+    /// When `with_sender_key` is set, our current sender key for the group is attached,
+    /// generating one from `rng` on first use.
+    fn send_group_control<R: CryptoRng + RngCore>(
+        &mut self,
+        to: &UserPublicInfo,
+        group_id: &str,
+        change: Option<GroupChange>,
+        with_sender_key: bool,
+        rng: &mut R,
+    ) -> EncryptedMessage {
+        let sender_key = with_sender_key.then(|| {
+            self.sender_keys
+                .entry(group_id.to_string())
+                .or_insert_with(|| SenderKeyState::new_with_rng(rng))
+                .distribution_message(group_id)
         });
+        let control = GroupControlMessage {
+            group_id: group_id.to_string(),
+            change,
+            sender_key,
+        };
+        self.encrypt_for(to, &control.encode(), false, rng)
+    }
+
+    /// Returns the public info of every member of `group` except ourselves.
+    fn other_members(&self, group: &Group) -> Vec<UserPublicInfo> {
+        group
+            .members()
+            .iter()
+            .filter(|m| m.info.id != self.id)
+            .map(|m| m.info.clone())
+            .collect()
+    }
 
-        ratchet.decrypt(msg)
+    /// Discards our own and received sender keys for `group_id`.
+    fn forget_group_keys(&mut self, group_id: &str) {
+        self.sender_keys.remove(group_id);
+        self.received_sender_keys
+            .retain(|(gid, _), _| gid != group_id);
     }
 }
 
@@ -1,4 +1,9 @@
-use crate::keys::one_time_prekey::OneTimePreKeyGroupPublic;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypto_utils::cipher_suite::CipherSuiteId,
+    keys::{kem_prekey::KemPreKeyPublic, one_time_prekey::OneTimePreKeyGroupPublic},
+};
 
 /// Represents the public information of a user required for the Signal protocol.
 ///
@@ -13,12 +18,50 @@ use crate::keys::one_time_prekey::OneTimePreKeyGroupPublic;
 /// - `id`: Unique identifier for the user.
 /// - `name`: Human-readable name of the user.
 /// - `ik`: Identity public key (used to verify long-term ownership).
+/// - `ik_sign`: Ed25519 identity public key verifying the pre-key signatures.
 /// - `spk`: Signed pre-key (ephemeral key signed by `ik`).
 /// - `opk`: One-time pre-key group used for forward secrecy.
+/// - `kem_spk`: Signed last-resort ML-KEM-768 pre-key (PQXDH).
+/// - `kem_opk`: Signed one-time ML-KEM-768 pre-keys (PQXDH).
+/// - `cipher_suites`: Cipher suites the user accepts for new sessions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserPublicInfo {
     pub id: String,
     pub name: String,
     pub ik: [u8; 32],
+    pub ik_sign: [u8; 32],
     pub spk: [u8; 32],
     pub opk: OneTimePreKeyGroupPublic,
+    pub kem_spk: KemPreKeyPublic,
+    pub kem_opk: Vec<KemPreKeyPublic>,
+    pub cipher_suites: Vec<CipherSuiteId>,
+}
+
+impl UserPublicInfo {
+    /// Selects the KEM pre-key an initiator should encapsulate to (non-consuming).
+    ///
+    /// A one-time KEM pre-key is preferred; the last-resort key is used once they are
+    /// exhausted. Keys whose signature does not verify under `ik_sign` are skipped.
+    ///
+    /// # Returns
+    /// The selected [`KemPreKeyPublic`], or `None` if no key carries a valid signature.
+    pub fn use_kem_prekey(&self) -> Option<KemPreKeyPublic> {
+        self.kem_opk
+            .first()
+            .into_iter()
+            .chain(std::iter::once(&self.kem_spk))
+            .find(|key| key.verify(&self.ik_sign))
+            .cloned()
+    }
+
+    /// Returns the last-resort KEM pre-key, for sessions started from a cached bundle whose
+    /// one-time KEM pre-keys may already be deleted.
+    ///
+    /// # Returns
+    /// The last-resort [`KemPreKeyPublic`], or `None` if its signature does not verify.
+    pub fn last_resort_kem_prekey(&self) -> Option<KemPreKeyPublic> {
+        self.kem_spk
+            .verify(&self.ik_sign)
+            .then(|| self.kem_spk.clone())
+    }
 }
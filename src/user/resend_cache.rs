@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// Number of recently sent messages kept for retransmission.
+const RESEND_CACHE_SIZE: usize = 64;
+
+/// A recently sent plaintext, kept so it can be re-encrypted on a retry request.
+///
+/// # Fields
+/// - `recipient_id`: User ID of the recipient.
+/// - `message_hash`: Hash of the ciphertext last sent for this plaintext.
+/// - `plaintext`: The payload that was encrypted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedMessage {
+    recipient_id: String,
+    message_hash: [u8; 32],
+    plaintext: Vec<u8>,
+}
+
+/// Bounded cache of the plaintexts of our most recent pairwise messages.
+///
+/// Once full, the oldest entry is evicted on every insertion.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct ResendCache {
+    entries: VecDeque<CachedMessage>,
+}
+
+impl ResendCache {
+    /// Records a sent message, evicting the oldest entry if the cache is full.
+    pub(crate) fn insert(&mut self, recipient_id: &str, message_hash: [u8; 32], plaintext: &[u8]) {
+        if self.entries.len() == RESEND_CACHE_SIZE {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(CachedMessage {
+            recipient_id: recipient_id.to_string(),
+            message_hash,
+            plaintext: plaintext.to_vec(),
+        });
+    }
+
+    /// Removes and returns the plaintext sent to `recipient_id` as `message_hash`.
+    ///
+    /// # Returns
+    /// The cached plaintext, or `None` if it was never sent or has been evicted.
+    pub(crate) fn take(&mut self, recipient_id: &str, message_hash: &[u8; 32]) -> Option<Vec<u8>> {
+        let position = self.entries.iter().position(|entry| {
+            entry.recipient_id == recipient_id && entry.message_hash == *message_hash
+        })?;
+        self.entries.remove(position).map(|entry| entry.plaintext)
+    }
+}
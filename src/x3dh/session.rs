@@ -1,10 +1,33 @@
-use crate::crypto_utils::{dh::diffie_hellman, hkdf::derive_session_key};
+use crate::crypto_utils::{
+    dh::diffie_hellman,
+    hkdf::derive_session_key,
+    signal_kdf::{self, KdfMode},
+};
 use crate::keys::one_time_prekey::OneTimePreKey;
 use crate::keys::{
     ephemeral_key::EphemeralKey, identity::IdentityKey, one_time_prekey::OneTimePreKeyPublic,
     session_key::SessionKey, signed_prekey::SignedPreKey,
 };
 
+/// Runs the X3DH KDF of `kdf_mode` over the concatenated shared secrets.
+///
+/// In [`KdfMode::Signal`], the session key is the root key and carries the initial
+/// chain key.
+fn session_key_from(
+    ikm: &[u8],
+    kdf_mode: KdfMode,
+    sender_name: String,
+    receiver_name: String,
+) -> SessionKey {
+    match kdf_mode {
+        KdfMode::Default => SessionKey::new(derive_session_key(ikm), sender_name, receiver_name),
+        KdfMode::Signal => {
+            let (root_key, chain_key) = signal_kdf::x3dh_keys(ikm);
+            SessionKey::new(root_key, sender_name, receiver_name).with_chain_key(chain_key)
+        }
+    }
+}
+
 /// Creates a new session key for the initiator (sender) in the X3DH protocol.
 ///
 /// Performs the required Diffie-Hellman (DH) operations between the initiator's identity
@@ -19,6 +42,9 @@ use crate::keys::{
 /// - `spk_receiver`: Receiver's signed pre-key (public).
 /// - `ik_receiver`: Receiver's identity key (public).
 /// - `opk_receiver`: Optional one-time pre-key (public).
+/// - `kem_shared_secret`: ML-KEM shared secret encapsulated to the receiver's KEM
+///   pre-key (PQXDH only).
+/// - `kdf_mode`: KDF constants of the session.
 ///
 /// # Returns
 /// A [`SessionKey`] object containing the derived shared secret and participant metadata.
@@ -28,9 +54,11 @@ use crate::keys::{
 /// - DH2: EK_initiator <-> IK_receiver
 /// - DH3: EK_initiator <-> SPK_receiver
 /// - DH4: EK_initiator <-> OPK_receiver (if present)
+/// - SS: KEM shared secret, appended last (if present)
 ///
 /// # Panics
 /// May panic if any cryptographic primitive fails unexpectedly.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn create_session_key(
     sender_name: String,
     receiver_name: String,
@@ -39,6 +67,8 @@ pub(crate) fn create_session_key(
     spk_receiver: [u8; 32],
     ik_receiver: [u8; 32],
     opk_receiver: Option<&OneTimePreKeyPublic>,
+    kem_shared_secret: Option<[u8; 32]>,
+    kdf_mode: KdfMode,
 ) -> SessionKey {
     let dh1 = diffie_hellman(&ik_initiator.get_private(), &spk_receiver);
     let dh2 = diffie_hellman(&ek_initiator.get_private(), &ik_receiver);
@@ -54,9 +84,11 @@ pub(crate) fn create_session_key(
         ikm.extend_from_slice(&dh4);
     }
 
-    let sk_bytes = derive_session_key(&ikm);
+    if let Some(ss) = kem_shared_secret {
+        ikm.extend_from_slice(&ss);
+    }
 
-    SessionKey::new(sk_bytes, sender_name, receiver_name)
+    session_key_from(&ikm, kdf_mode, sender_name, receiver_name)
 }
 
 /// Derives a session key for the receiver (responder) in the X3DH protocol.
@@ -70,9 +102,12 @@ pub(crate) fn create_session_key(
 /// - `sender_name`: Sender's ID or name.
 /// - `receiver_ik`: Receiver’s identity key (private).
 /// - `receiver_spk`: Receiver’s signed pre-key (private).
-/// - `receiver_opk`: Receiver’s one-time pre-key (private).
+/// - `receiver_opk`: Receiver’s one-time pre-key (private), if the sender used one.
 /// - `sender_ik_public`: Sender's identity key (public).
 /// - `sender_ek_public`: Sender's ephemeral key (public).
+/// - `kem_shared_secret`: ML-KEM shared secret decapsulated from the sender's
+///   ciphertext (PQXDH only).
+/// - `kdf_mode`: KDF constants of the session.
 ///
 /// # Returns
 /// A [`SessionKey`] derived from the DH shared secrets.
@@ -81,26 +116,35 @@ pub(crate) fn create_session_key(
 /// - DH1: SPK_receiver <-> IK_sender
 /// - DH2: IK_receiver <-> EK_sender
 /// - DH3: SPK_receiver <-> EK_sender
-/// - DH4: OPK_receiver <-> EK_sender
+/// - DH4: OPK_receiver <-> EK_sender (if an OPK was used)
+/// - SS: KEM shared secret, appended last (if present)
 ///
 /// # Panics
 /// May panic if any of the internal cryptographic functions fail unexpectedly.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn receive_session_key(
     receiver_name: String,
     sender_name: String,
     receiver_ik: &IdentityKey,
     receiver_spk: &SignedPreKey,
-    receiver_opk: OneTimePreKey,
+    receiver_opk: Option<&OneTimePreKey>,
     sender_ik_public: [u8; 32],
     sender_ek_public: [u8; 32],
+    kem_shared_secret: Option<[u8; 32]>,
+    kdf_mode: KdfMode,
 ) -> SessionKey {
     let dh1 = diffie_hellman(&receiver_spk.get_private(), &sender_ik_public);
     let dh2 = diffie_hellman(&receiver_ik.get_private(), &sender_ek_public);
     let dh3 = diffie_hellman(&receiver_spk.get_private(), &sender_ek_public);
-    let dh4 = diffie_hellman(&receiver_opk.get_private(), &sender_ek_public);
 
-    let ikm = [dh1, dh2, dh3, dh4].concat();
-    let sk_bytes = derive_session_key(&ikm);
+    let mut ikm = [dh1, dh2, dh3].concat();
+    if let Some(opk) = receiver_opk {
+        let dh4 = diffie_hellman(&opk.get_private(), &sender_ek_public);
+        ikm.extend_from_slice(&dh4);
+    }
+    if let Some(ss) = kem_shared_secret {
+        ikm.extend_from_slice(&ss);
+    }
 
-    SessionKey::new(sk_bytes, sender_name, receiver_name)
+    session_key_from(&ikm, kdf_mode, sender_name, receiver_name)
 }
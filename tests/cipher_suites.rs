@@ -0,0 +1,125 @@
+//! Every cipher suite must carry a full session, and negotiation must fall back to the
+//! default suite when the responder does not support the preferred one.
+
+use signal_protocol_poc::{
+    User, crypto_utils::cipher_suite::CipherSuiteId, double_ratchet::config::SessionConfig,
+};
+
+/// Runs a conversation with out-of-order delivery and DH ratchet steps in both directions.
+fn converse(alice: &mut User, bob: &mut User) {
+    let a1 = alice.send_message(&bob.public_info(), "a1");
+    let a2 = alice.send_message(&bob.public_info(), "a2");
+    let a3 = alice.send_message(&bob.public_info(), "a3");
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &a1).as_deref(),
+        Some("a1")
+    );
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &a3).as_deref(),
+        Some("a3")
+    );
+
+    for round in 0..3 {
+        let reply = bob.send_message(&alice.public_info(), &format!("b{round}"));
+        assert_eq!(
+            alice.receive_message(&bob.public_info(), &reply),
+            Some(format!("b{round}"))
+        );
+        let msg = alice.send_message(&bob.public_info(), &format!("a{round}"));
+        assert_eq!(
+            bob.receive_message(&alice.public_info(), &msg),
+            Some(format!("a{round}"))
+        );
+    }
+
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &a2).as_deref(),
+        Some("a2")
+    );
+}
+
+fn round_trip(cipher_suite: CipherSuiteId, header_encryption: bool) {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    alice.set_session_config(SessionConfig {
+        cipher_suite,
+        header_encryption,
+        ..SessionConfig::default()
+    });
+
+    converse(&mut alice, &mut bob);
+    assert_eq!(
+        alice.session_info(&bob.id).unwrap().cipher_suite,
+        cipher_suite
+    );
+    assert_eq!(
+        bob.session_info(&alice.id).unwrap().cipher_suite,
+        cipher_suite
+    );
+}
+
+#[test]
+fn every_suite_round_trips() {
+    for suite in CipherSuiteId::ALL {
+        round_trip(suite, false);
+    }
+}
+
+#[test]
+fn every_suite_round_trips_with_header_encryption() {
+    for suite in CipherSuiteId::ALL {
+        round_trip(suite, true);
+    }
+}
+
+#[test]
+fn negotiate_prefers_supported_suite() {
+    for suite in CipherSuiteId::ALL {
+        assert_eq!(CipherSuiteId::negotiate(suite, &CipherSuiteId::ALL), suite);
+        assert_eq!(CipherSuiteId::negotiate(suite, &[suite]), suite);
+    }
+}
+
+#[test]
+fn negotiate_falls_back_to_default_suite() {
+    assert_eq!(
+        CipherSuiteId::negotiate(
+            CipherSuiteId::Aes256GcmSha256,
+            &[
+                CipherSuiteId::ChaCha20Poly1305Sha256,
+                CipherSuiteId::XChaCha20Poly1305Sha512
+            ],
+        ),
+        CipherSuiteId::ChaCha20Poly1305Sha256
+    );
+    assert_eq!(
+        CipherSuiteId::negotiate(CipherSuiteId::XChaCha20Poly1305Sha512, &[]),
+        CipherSuiteId::ChaCha20Poly1305Sha256
+    );
+}
+
+#[test]
+fn session_falls_back_when_peer_lacks_preferred_suite() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    alice.set_session_config(SessionConfig {
+        cipher_suite: CipherSuiteId::Aes256GcmSha256,
+        ..SessionConfig::default()
+    });
+
+    let mut bundle = bob.public_info();
+    bundle.cipher_suites = vec![CipherSuiteId::ChaCha20Poly1305Sha256];
+    let msg = alice.send_message(&bundle, "hello");
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &msg).as_deref(),
+        Some("hello")
+    );
+
+    converse(&mut alice, &mut bob);
+    for info in [alice.session_info(&bob.id), bob.session_info(&alice.id)] {
+        assert_eq!(
+            info.unwrap().cipher_suite,
+            CipherSuiteId::ChaCha20Poly1305Sha256
+        );
+    }
+}
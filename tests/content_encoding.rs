@@ -0,0 +1,100 @@
+//! Every content variant must survive an encode/decode round trip, and encodings of other
+//! versions or with malformed framing must be rejected.
+
+use chrono::{DateTime, Utc};
+use signal_protocol_poc::content::{Content, ContentMessage, ReceiptKind, TypingAction};
+
+fn all_contents() -> Vec<Content> {
+    vec![
+        Content::text("hello"),
+        Content::text(""),
+        Content::Reaction {
+            emoji: "👍".to_string(),
+            target_id: "target".to_string(),
+            remove: true,
+        },
+        Content::Receipt {
+            kind: ReceiptKind::Read,
+            message_ids: vec!["a".to_string(), "b".to_string()],
+        },
+        Content::Receipt {
+            kind: ReceiptKind::Delivery,
+            message_ids: Vec::new(),
+        },
+        Content::Typing {
+            action: TypingAction::Stopped,
+        },
+        Content::Edit {
+            target_id: "target".to_string(),
+            body: "edited".to_string(),
+        },
+        Content::Delete {
+            target_id: "target".to_string(),
+        },
+        Content::EndSession,
+        Content::ExpirationTimerUpdate { seconds: 3600 },
+    ]
+}
+
+#[test]
+fn every_variant_round_trips() {
+    for content in all_contents() {
+        let message = ContentMessage::new(content, 30);
+        assert_eq!(ContentMessage::decode(&message.encode()), Some(message));
+    }
+}
+
+#[test]
+fn encoding_is_stable() {
+    let message = ContentMessage {
+        id: "id".to_string(),
+        timestamp: DateTime::<Utc>::from_timestamp_millis(1_000).unwrap(),
+        expire_timer: 5,
+        content: Content::text("hi"),
+    };
+    let expected = [
+        vec![4],
+        vec![0, 0, 0, 2],
+        b"id".to_vec(),
+        1_000i64.to_be_bytes().to_vec(),
+        vec![0, 0, 0, 5],
+        vec![0],
+        vec![0, 0, 0, 2],
+        b"hi".to_vec(),
+    ]
+    .concat();
+    assert_eq!(message.encode(), expected);
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let encoded = ContentMessage::new(Content::text("hello"), 0).encode();
+    for version in [0, 1, 2, 3, 5] {
+        let mut other = encoded.clone();
+        other[0] = version;
+        assert_eq!(ContentMessage::decode(&other), None);
+    }
+}
+
+#[test]
+fn malformed_framing_is_rejected() {
+    let encoded = ContentMessage::new(Content::text("hello"), 0).encode();
+    assert_eq!(ContentMessage::decode(&encoded[..encoded.len() - 1]), None);
+
+    let mut trailing = encoded.clone();
+    trailing.push(0);
+    assert_eq!(ContentMessage::decode(&trailing), None);
+    assert_eq!(ContentMessage::decode(&[]), None);
+}
+
+#[test]
+fn unrepresentable_expiry_is_rejected() {
+    let mut message = ContentMessage::new(Content::text("hello"), 60);
+    message.timestamp = DateTime::<Utc>::MAX_UTC;
+    assert_eq!(message.expires_at(), None);
+    assert_eq!(ContentMessage::decode(&message.encode()), None);
+
+    message.expire_timer = 0;
+    let decoded = ContentMessage::decode(&message.encode()).unwrap();
+    assert_eq!(decoded.expires_at(), None);
+}
@@ -0,0 +1,137 @@
+//! A seeded RNG must reproduce users and transcripts byte for byte.
+
+use rand::{SeedableRng, rngs::StdRng};
+use signal_protocol_poc::{
+    User,
+    content::{Content, ContentMessage},
+    double_ratchet::config::SessionConfig,
+    sealed_sender::certificate::TrustRoot,
+    treekem::{
+        group::TreeKemGroup,
+        messages::{KeyPackageBundle, Proposal},
+    },
+};
+
+fn transcript(seed: u64, config: SessionConfig) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut alice = User::new_with_rng("Alice".to_string(), &mut rng);
+    let mut bob = User::new_with_rng("Bob".to_string(), &mut rng);
+    alice.set_session_config(config);
+
+    let mut transcript = vec![alice.id.clone(), bob.id.clone()];
+    for round in 0..4 {
+        let msg =
+            alice.send_message_with_rng(&bob.public_info(), &format!("ping {round}"), &mut rng);
+        assert!(bob.receive_message(&alice.public_info(), &msg).is_some());
+        let reply =
+            bob.send_message_with_rng(&alice.public_info(), &format!("pong {round}"), &mut rng);
+        assert!(alice.receive_message(&bob.public_info(), &reply).is_some());
+
+        transcript.push(serde_json::to_string(&msg).unwrap());
+        transcript.push(serde_json::to_string(&reply).unwrap());
+    }
+    transcript
+}
+
+#[test]
+fn same_seed_same_transcript() {
+    let config = SessionConfig::default();
+    assert_eq!(transcript(42, config), transcript(42, config));
+    assert_ne!(transcript(42, config), transcript(43, config));
+}
+
+#[test]
+fn same_seed_same_transcript_with_all_options() {
+    let config = SessionConfig {
+        header_encryption: true,
+        pqxdh: true,
+        pq_ratchet: true,
+        ..SessionConfig::default()
+    };
+    assert_eq!(transcript(7, config), transcript(7, config));
+}
+
+fn group_transcript(seed: u64) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut alice = User::new_with_rng("Alice".to_string(), &mut rng);
+    let bob = User::new_with_rng("Bob".to_string(), &mut rng);
+
+    let (group_id, outgoing) =
+        alice.create_group_with_rng("friends", &[bob.public_info()], &mut rng);
+    let mut transcript = vec![group_id];
+    transcript.extend(
+        outgoing
+            .iter()
+            .map(|(to_id, msg)| format!("{to_id}:{}", serde_json::to_string(msg).unwrap())),
+    );
+    transcript
+}
+
+#[test]
+fn same_seed_same_group() {
+    assert_eq!(group_transcript(42), group_transcript(42));
+    assert_ne!(group_transcript(42), group_transcript(43));
+}
+
+fn treekem_transcript(seed: u64) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let alice = KeyPackageBundle::new_with_rng("Alice", &mut rng);
+    let bob = KeyPackageBundle::new_with_rng("Bob", &mut rng);
+
+    let mut group = TreeKemGroup::create_with_rng("group", &alice, &mut rng);
+    let (add, welcomes) = group
+        .commit_with_rng(vec![Proposal::Add(bob.key_package.clone())], &mut rng)
+        .unwrap();
+    let update = group.update_with_rng(&mut rng);
+    let msg = group.encrypt_with_rng(b"hello", &mut rng);
+
+    vec![
+        serde_json::to_string(&bob.key_package).unwrap(),
+        serde_json::to_string(&add).unwrap(),
+        serde_json::to_string(&welcomes).unwrap(),
+        serde_json::to_string(&update).unwrap(),
+        serde_json::to_string(&msg).unwrap(),
+    ]
+}
+
+#[test]
+fn same_seed_same_treekem_transcript() {
+    assert_eq!(treekem_transcript(42), treekem_transcript(42));
+    assert_ne!(treekem_transcript(42), treekem_transcript(43));
+}
+
+#[test]
+fn same_seed_same_certificates() {
+    let issue = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let root = TrustRoot::new_with_rng(&mut rng);
+        let server = root.issue_server_with_rng(1, &mut rng);
+        (root.public_key(), server.certificate.key)
+    };
+    assert_eq!(issue(42), issue(42));
+    assert_ne!(issue(42), issue(43));
+}
+
+#[test]
+fn same_seed_same_message_ids() {
+    let ids = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut alice = User::new_with_rng("Alice".to_string(), &mut rng);
+        let bob = User::new_with_rng("Bob".to_string(), &mut rng);
+        (0..3)
+            .map(|_| {
+                alice
+                    .send_content_with_rng(&bob.public_info(), Content::text("hi"), &mut rng)
+                    .0
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids(42), ids(42));
+    assert_ne!(ids(42), ids(43));
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let first = ContentMessage::new_with_rng(Content::text("hi"), 0, &mut rng);
+    let mut rng = StdRng::seed_from_u64(42);
+    let second = ContentMessage::new_with_rng(Content::text("hi"), 0, &mut rng);
+    assert_eq!(first.id, second.id);
+}
@@ -0,0 +1,51 @@
+//! Disappearing messages carry their expiry, so sender and recipient schedule them alike.
+
+use chrono::{DateTime, Duration, Utc};
+use signal_protocol_poc::{
+    User,
+    content::{Content, ContentMessage},
+};
+
+#[test]
+fn sender_and_recipient_share_the_expiry() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+
+    let update = alice.set_expiration_timer(&bob.public_info(), 60);
+    assert!(bob.receive_content(&alice.public_info(), &update).is_some());
+    assert_eq!(bob.expiration_timer(&alice.id), 60);
+
+    let (id, msg) = alice.send_content(&bob.public_info(), Content::text("secret"));
+    let received = bob.receive_content(&alice.public_info(), &msg).unwrap();
+    assert_eq!(received.message_id, id);
+    assert_eq!(
+        received.expires_at,
+        Some(received.timestamp + Duration::seconds(60))
+    );
+
+    let sent = alice.expiry_schedule().iter().find(|m| m.message_id == id);
+    let stored = bob.expiry_schedule().iter().find(|m| m.message_id == id);
+    assert_eq!(sent.map(|m| m.expires_at), stored.map(|m| m.expires_at));
+    assert_eq!(stored.map(|m| m.expires_at), received.expires_at);
+
+    assert!(bob.take_expired(Utc::now()).is_empty());
+    let expired = bob.take_expired(Utc::now() + Duration::seconds(61));
+    assert!(expired.iter().any(|m| m.message_id == id));
+}
+
+#[test]
+fn unrepresentable_expiry_is_rejected_without_panicking() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    let (_, hello) = alice.send_content(&bob.public_info(), Content::text("hello"));
+    assert!(bob.receive_content(&alice.public_info(), &hello).is_some());
+
+    let mut message = ContentMessage::new(Content::text("boom"), u32::MAX);
+    message.timestamp = DateTime::<Utc>::MAX_UTC;
+    let msg = alice.send_bytes(&bob.public_info(), &message.encode());
+    assert!(bob.receive_content(&alice.public_info(), &msg).is_none());
+    assert!(bob.expiry_schedule().is_empty());
+
+    let (_, msg) = alice.send_content(&bob.public_info(), Content::text("fine"));
+    assert!(bob.receive_content(&alice.public_info(), &msg).is_some());
+}
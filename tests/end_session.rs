@@ -0,0 +1,52 @@
+//! Ending a session archives it on both sides, and a delayed end-session message must not
+//! tear down the session that replaced it.
+
+use signal_protocol_poc::{User, content::Content};
+
+fn text(body: &str) -> Content {
+    Content::Text {
+        body: body.to_string(),
+    }
+}
+
+#[test]
+fn end_session_archives_both_sides() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+
+    let (_, hello) = alice.send_content(&bob.public_info(), text("hello"));
+    assert!(bob.receive_content(&alice.public_info(), &hello).is_some());
+
+    let end = alice.end_session(&bob.public_info());
+    assert!(alice.session_info(&bob.id).is_none());
+    let received = bob.receive_content(&alice.public_info(), &end).unwrap();
+    assert_eq!(received.content, Content::EndSession);
+    assert!(bob.session_info(&alice.id).is_none());
+
+    let (_, again) = alice.send_content(&bob.public_info(), text("again"));
+    let received = bob.receive_content(&alice.public_info(), &again).unwrap();
+    assert_eq!(received.content, text("again"));
+}
+
+#[test]
+fn delayed_end_session_keeps_new_session() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+
+    let (_, hello) = alice.send_content(&bob.public_info(), text("hello"));
+    assert!(bob.receive_content(&alice.public_info(), &hello).is_some());
+
+    let end = alice.end_session(&bob.public_info());
+    let (_, fresh) = alice.send_content(&bob.public_info(), text("fresh"));
+    assert!(bob.receive_content(&alice.public_info(), &fresh).is_some());
+    let session = bob.session_info(&alice.id);
+    assert!(session.is_some());
+
+    let received = bob.receive_content(&alice.public_info(), &end).unwrap();
+    assert_eq!(received.content, Content::EndSession);
+    assert_eq!(bob.session_info(&alice.id), session);
+
+    let (_, next) = alice.send_content(&bob.public_info(), text("next"));
+    let received = bob.receive_content(&alice.public_info(), &next).unwrap();
+    assert_eq!(received.content, text("next"));
+}
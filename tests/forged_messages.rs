@@ -0,0 +1,77 @@
+//! Forged or corrupted messages must be rejected cheaply and leave the session unchanged.
+
+use signal_protocol_poc::{
+    User, double_ratchet::config::SessionConfig, error::DecryptError,
+    keys::encrypted_message::EncryptedMessage,
+};
+
+/// Establishes a session in both directions and returns the next message from Alice.
+fn established(config: SessionConfig) -> (User, User, EncryptedMessage) {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    alice.set_session_config(config);
+
+    let hello = alice.send_message(&bob.public_info(), "hello");
+    assert!(bob.receive_message(&alice.public_info(), &hello).is_some());
+    let reply = bob.send_message(&alice.public_info(), "hi");
+    assert!(alice.receive_message(&bob.public_info(), &reply).is_some());
+
+    let next = alice.send_message(&bob.public_info(), "next");
+    (alice, bob, next)
+}
+
+/// Delivers `forged` to Bob and checks that it changes nothing before `next` is delivered.
+fn assert_rejected_without_effect(
+    alice: &User,
+    bob: &mut User,
+    forged: &EncryptedMessage,
+    next: &EncryptedMessage,
+) {
+    let before = bob.session_info(&alice.id);
+    assert_eq!(
+        bob.try_receive_bytes(&alice.public_info(), forged),
+        Err(DecryptError::InvalidMessage)
+    );
+    assert_eq!(bob.session_info(&alice.id), before);
+    assert_eq!(
+        bob.try_receive_bytes(&alice.public_info(), next),
+        Ok(b"next".to_vec())
+    );
+}
+
+#[test]
+fn huge_skip_with_fresh_ratchet_key_is_rejected() {
+    let (alice, mut bob, next) = established(SessionConfig::default());
+    let mut forged = next.clone();
+    forged.ratchet_pub = [7u8; 32];
+    forged.message_index = u32::MAX;
+    forged.previous_counter = u32::MAX;
+
+    assert_rejected_without_effect(&alice, &mut bob, &forged, &next);
+}
+
+#[test]
+fn huge_skip_on_current_chain_is_rejected() {
+    let (alice, mut bob, next) = established(SessionConfig::default());
+    let mut forged = next.clone();
+    forged.message_index = u32::MAX;
+
+    assert_rejected_without_effect(&alice, &mut bob, &forged, &next);
+}
+
+#[test]
+fn corrupted_ciphertext_is_rejected() {
+    for config in [
+        SessionConfig::default(),
+        SessionConfig {
+            header_encryption: true,
+            ..SessionConfig::default()
+        },
+    ] {
+        let (alice, mut bob, next) = established(config);
+        let mut forged = next.clone();
+        forged.ciphertext[0] ^= 1;
+
+        assert_rejected_without_effect(&alice, &mut bob, &forged, &next);
+    }
+}
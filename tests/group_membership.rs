@@ -0,0 +1,132 @@
+//! Sender keys and membership changes must only be accepted from members of a group we
+//! know.
+
+use std::collections::VecDeque;
+
+use signal_protocol_poc::{User, keys::encrypted_message::EncryptedMessage};
+
+/// Delivers group control messages, and the replies they trigger, until none are left.
+///
+/// # Returns
+/// The number of messages that were rejected.
+fn deliver(users: &mut [User], from: usize, outgoing: Vec<(String, EncryptedMessage)>) -> usize {
+    let mut queue: VecDeque<_> = outgoing
+        .into_iter()
+        .map(|(to_id, msg)| (from, to_id, msg))
+        .collect();
+    let mut rejected = 0;
+    while let Some((from, to_id, msg)) = queue.pop_front() {
+        let to = users.iter().position(|user| user.id == to_id).unwrap();
+        let from_info = users[from].public_info();
+        match users[to].receive_group_control(&from_info, &msg) {
+            Some(result) => queue.extend(
+                result
+                    .replies
+                    .into_iter()
+                    .map(|(to_id, msg)| (to, to_id, msg)),
+            ),
+            None => rejected += 1,
+        }
+    }
+    rejected
+}
+
+fn users(names: &[&str]) -> Vec<User> {
+    names
+        .iter()
+        .map(|name| User::new(name.to_string()))
+        .collect()
+}
+
+fn can_read(users: &mut [User], sender: usize, receiver: usize, group_id: &str) -> bool {
+    let Some(msg) = users[sender].send_group_message(group_id, "hi") else {
+        return false;
+    };
+    let sender_info = users[sender].public_info();
+    users[receiver]
+        .receive_group_message(&sender_info, &msg)
+        .as_deref()
+        == Some("hi")
+}
+
+#[test]
+fn members_exchange_sender_keys_on_create() {
+    let mut users = users(&["Alice", "Bob", "Carol"]);
+    let members = [users[1].public_info(), users[2].public_info()];
+    let (group_id, outgoing) = users[0].create_group("friends", &members);
+    assert_eq!(deliver(&mut users, 0, outgoing), 0);
+
+    for sender in 0..3 {
+        for receiver in (0..3).filter(|&receiver| receiver != sender) {
+            assert!(can_read(&mut users, sender, receiver, &group_id));
+        }
+    }
+}
+
+#[test]
+fn sender_key_for_unknown_group_is_ignored() {
+    let mut users = users(&["Bob", "Mallory"]);
+    let bob_info = users[0].public_info();
+    let mallory_info = users[1].public_info();
+
+    let msg = users[1].send_sender_key(&bob_info, "unknown-group");
+    assert_eq!(users[0].receive_sender_key(&mallory_info, &msg), None);
+    assert!(!can_read(&mut users, 1, 0, "unknown-group"));
+}
+
+#[test]
+fn sender_key_from_non_member_is_ignored() {
+    let mut users = users(&["Alice", "Bob", "Mallory"]);
+    let members = [users[1].public_info()];
+    let (group_id, outgoing) = users[0].create_group("friends", &members);
+    deliver(&mut users, 0, outgoing);
+
+    let bob_info = users[1].public_info();
+    let mallory_info = users[2].public_info();
+    let msg = users[2].send_sender_key(&bob_info, &group_id);
+    assert_eq!(users[1].receive_sender_key(&mallory_info, &msg), None);
+    assert!(!can_read(&mut users, 2, 1, &group_id));
+}
+
+#[test]
+fn sender_key_from_member_is_received() {
+    let mut users = users(&["Alice", "Bob"]);
+    let members = [users[1].public_info()];
+    let (group_id, outgoing) = users[0].create_group("friends", &members);
+    deliver(&mut users, 0, outgoing);
+
+    let alice_info = users[0].public_info();
+    let bob_info = users[1].public_info();
+    let msg = users[0].send_sender_key(&bob_info, &group_id);
+    assert_eq!(
+        users[1].receive_sender_key(&alice_info, &msg),
+        Some(group_id.clone())
+    );
+    assert!(can_read(&mut users, 0, 1, &group_id));
+}
+
+#[test]
+fn leave_from_non_member_does_not_rotate_keys() {
+    let mut users = users(&["Alice", "Bob", "Mallory"]);
+    let members = [users[1].public_info(), users[2].public_info()];
+    let (group_id, outgoing) = users[0].create_group("friends", &members);
+    deliver(&mut users, 0, outgoing);
+
+    // Mallory never processes her removal, so she still believes she is a member.
+    let mallory_id = users[2].id.clone();
+    let outgoing = users[0]
+        .remove_group_member(&group_id, &mallory_id)
+        .unwrap();
+    let outgoing = outgoing
+        .into_iter()
+        .filter(|(to_id, _)| *to_id != mallory_id)
+        .collect();
+    assert_eq!(deliver(&mut users, 0, outgoing), 0);
+
+    let outgoing = users[2].leave_group(&group_id).unwrap();
+    assert_eq!(deliver(&mut users, 2, outgoing), 2);
+
+    assert!(can_read(&mut users, 0, 1, &group_id));
+    assert!(can_read(&mut users, 1, 0, &group_id));
+    assert_eq!(users[0].group(&group_id).unwrap().members().len(), 2);
+}
@@ -0,0 +1,189 @@
+//! One-time pre-keys are deleted once the session they established decrypts its first
+//! message, while group members keep starting sessions from cached bundles.
+
+use std::collections::VecDeque;
+
+use signal_protocol_poc::{
+    User, double_ratchet::config::SessionConfig, error::DecryptError,
+    keys::encrypted_message::EncryptedMessage,
+};
+
+fn opk_count(user: &User) -> usize {
+    user.public_info().opk.keys.len()
+}
+
+#[test]
+fn one_time_prekey_is_deleted_after_first_decrypt() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    let count = opk_count(&bob);
+
+    let msg = alice.send_message(&bob.public_info(), "hello");
+    let used = msg.opk_used.unwrap();
+    assert!(bob.receive_message(&alice.public_info(), &msg).is_some());
+
+    assert_eq!(opk_count(&bob), count - 1);
+    assert!(bob.public_info().opk.keys.iter().all(|k| k.public != used));
+}
+
+#[test]
+fn failed_first_message_keeps_one_time_prekey() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    let count = opk_count(&bob);
+
+    let msg = alice.send_message(&bob.public_info(), "hello");
+    let mut forged = msg.clone();
+    forged.ciphertext[0] ^= 1;
+    assert_eq!(
+        bob.try_receive_bytes(&alice.public_info(), &forged),
+        Err(DecryptError::InvalidMessage)
+    );
+    assert_eq!(opk_count(&bob), count);
+
+    assert!(bob.receive_message(&alice.public_info(), &msg).is_some());
+    assert_eq!(opk_count(&bob), count - 1);
+}
+
+#[test]
+fn stale_bundle_cannot_reuse_deleted_prekey() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    let mut carol = User::new("Carol".to_string());
+    let stale = bob.public_info();
+
+    let msg = alice.send_message(&stale, "hello");
+    assert!(bob.receive_message(&alice.public_info(), &msg).is_some());
+
+    let replayed_key = carol.send_message(&stale, "hi");
+    assert_eq!(
+        bob.try_receive_bytes(&carol.public_info(), &replayed_key),
+        Err(DecryptError::InvalidPreKeyMessage)
+    );
+
+    carol.reset_session(&bob.id);
+    let fresh = carol.send_message(&bob.public_info(), "hi");
+    assert_eq!(
+        bob.receive_message(&carol.public_info(), &fresh).as_deref(),
+        Some("hi")
+    );
+}
+
+fn pqxdh_user(name: &str) -> User {
+    let mut user = User::new(name.to_string());
+    user.set_session_config(SessionConfig {
+        pqxdh: true,
+        ..SessionConfig::default()
+    });
+    user
+}
+
+#[test]
+fn one_time_kem_prekey_is_deleted_after_first_decrypt() {
+    let mut alice = pqxdh_user("Alice");
+    let mut bob = User::new("Bob".to_string());
+    let mut carol = pqxdh_user("Carol");
+    let count = bob.public_info().kem_opk.len();
+
+    let first = alice.send_message(&bob.public_info(), "hello");
+    let used = first.kem_prekey_id.clone().unwrap();
+    assert!(bob.receive_message(&alice.public_info(), &first).is_some());
+
+    let bundle = bob.public_info();
+    assert_eq!(bundle.kem_opk.len(), count - 1);
+    assert!(bundle.kem_opk.iter().all(|key| key.id != used));
+
+    let second = carol.send_message(&bundle, "hi");
+    assert_ne!(second.kem_prekey_id.as_deref(), Some(used.as_str()));
+    assert_ne!(
+        second.kem_prekey_id.as_deref(),
+        Some(bundle.kem_spk.id.as_str())
+    );
+    assert!(bob.receive_message(&carol.public_info(), &second).is_some());
+}
+
+#[test]
+fn stale_bundle_cannot_reuse_deleted_kem_prekey() {
+    let mut alice = pqxdh_user("Alice");
+    let mut bob = User::new("Bob".to_string());
+    let mut carol = pqxdh_user("Carol");
+    let stale = bob.public_info();
+
+    let msg = alice.send_message(&stale, "hello");
+    assert!(bob.receive_message(&alice.public_info(), &msg).is_some());
+
+    let mut stale_without_opk = stale.clone();
+    stale_without_opk.opk.keys.clear();
+    let replayed_key = carol.send_message(&stale_without_opk, "hi");
+    assert_eq!(replayed_key.kem_prekey_id, msg.kem_prekey_id);
+    assert_eq!(
+        bob.try_receive_bytes(&carol.public_info(), &replayed_key),
+        Err(DecryptError::InvalidPreKeyMessage)
+    );
+}
+
+/// Delivers group control messages, and the replies they trigger, until none are left.
+fn deliver(users: &mut [User], from: usize, outgoing: Vec<(String, EncryptedMessage)>) {
+    let mut queue: VecDeque<_> = outgoing
+        .into_iter()
+        .map(|(to_id, msg)| (from, to_id, msg))
+        .collect();
+    while let Some((from, to_id, msg)) = queue.pop_front() {
+        let to = users.iter().position(|user| user.id == to_id).unwrap();
+        let from_info = users[from].public_info();
+        let result = users[to]
+            .receive_group_control(&from_info, &msg)
+            .expect("group control message rejected");
+        queue.extend(
+            result
+                .replies
+                .into_iter()
+                .map(|(to_id, msg)| (to, to_id, msg)),
+        );
+    }
+}
+
+fn group_from_cached_bundles(mut users: Vec<User>) {
+    let carol_bundle = users[2].public_info();
+
+    let msg = users[0].send_message(&carol_bundle, "hello");
+    let alice_info = users[0].public_info();
+    assert!(users[2].receive_message(&alice_info, &msg).is_some());
+
+    let members = [users[1].public_info(), carol_bundle];
+    let (group_id, outgoing) = users[0].create_group("friends", &members);
+    deliver(&mut users, 0, outgoing);
+
+    for sender in 0..users.len() {
+        let msg = users[sender]
+            .send_group_message(&group_id, "hi all")
+            .unwrap();
+        let sender_info = users[sender].public_info();
+        for receiver in (0..users.len()).filter(|&receiver| receiver != sender) {
+            assert_eq!(
+                users[receiver]
+                    .receive_group_message(&sender_info, &msg)
+                    .as_deref(),
+                Some("hi all")
+            );
+        }
+    }
+}
+
+#[test]
+fn group_members_start_sessions_from_cached_bundles() {
+    group_from_cached_bundles(vec![
+        User::new("Alice".to_string()),
+        User::new("Bob".to_string()),
+        User::new("Carol".to_string()),
+    ]);
+}
+
+#[test]
+fn group_members_start_pqxdh_sessions_from_cached_bundles() {
+    group_from_cached_bundles(vec![
+        pqxdh_user("Alice"),
+        pqxdh_user("Bob"),
+        pqxdh_user("Carol"),
+    ]);
+}
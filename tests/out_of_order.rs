@@ -0,0 +1,74 @@
+//! Delayed messages must decrypt across DH ratchet steps, and only messages whose key was
+//! actually consumed may be reported as duplicates.
+
+use signal_protocol_poc::{User, double_ratchet::config::SessionConfig, error::DecryptError};
+
+fn delayed_message_across_ratchet_step(config: SessionConfig) {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    alice.set_session_config(config);
+
+    let a1 = alice.send_message(&bob.public_info(), "a1");
+    let a2 = alice.send_message(&bob.public_info(), "a2");
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &a1).as_deref(),
+        Some("a1")
+    );
+
+    let b1 = bob.send_message(&alice.public_info(), "b1");
+    assert_eq!(
+        alice.receive_message(&bob.public_info(), &b1).as_deref(),
+        Some("b1")
+    );
+    let a3 = alice.send_message(&bob.public_info(), "a3");
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &a3).as_deref(),
+        Some("a3")
+    );
+
+    assert_eq!(
+        bob.try_receive_bytes(&alice.public_info(), &a2),
+        Ok(b"a2".to_vec())
+    );
+    assert_eq!(
+        bob.try_receive_bytes(&alice.public_info(), &a2),
+        Err(DecryptError::DuplicateMessage)
+    );
+    assert_eq!(
+        bob.try_receive_bytes(&alice.public_info(), &a1),
+        Err(DecryptError::DuplicateMessage)
+    );
+}
+
+#[test]
+fn delayed_message_decrypts_after_ratchet_step() {
+    delayed_message_across_ratchet_step(SessionConfig::default());
+}
+
+#[test]
+fn delayed_message_decrypts_after_ratchet_step_with_header_encryption() {
+    delayed_message_across_ratchet_step(SessionConfig {
+        header_encryption: true,
+        ..SessionConfig::default()
+    });
+}
+
+#[test]
+fn duplicate_is_rejected_on_current_chain() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+
+    let a1 = alice.send_message(&bob.public_info(), "a1");
+    let a2 = alice.send_message(&bob.public_info(), "a2");
+    let a3 = alice.send_message(&bob.public_info(), "a3");
+    assert!(bob.receive_message(&alice.public_info(), &a1).is_some());
+    assert!(bob.receive_message(&alice.public_info(), &a3).is_some());
+    assert!(bob.receive_message(&alice.public_info(), &a2).is_some());
+
+    for msg in [&a1, &a2, &a3] {
+        assert_eq!(
+            bob.try_receive_bytes(&alice.public_info(), msg),
+            Err(DecryptError::DuplicateMessage)
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Padding must round-trip any plaintext, and the padding mode a session uses must not be
+//! alterable in transit.
+
+use signal_protocol_poc::{
+    User, crypto_utils::padding::Padding, double_ratchet::config::SessionConfig,
+    error::DecryptError,
+};
+
+const SCHEMES: [Padding; 3] = [Padding::None, Padding::Signal160, Padding::Padme];
+
+fn plaintexts() -> Vec<Vec<u8>> {
+    vec![
+        Vec::new(),
+        b"hello".to_vec(),
+        vec![0x80],
+        vec![0x00],
+        b"ends with marker\x80".to_vec(),
+        b"ends with zeros\x80\x00\x00".to_vec(),
+        vec![0u8; 159],
+        vec![0x80; 160],
+        vec![0xAB; 1000],
+    ]
+}
+
+#[test]
+fn pad_then_unpad_round_trips() {
+    for padding in SCHEMES {
+        for plaintext in plaintexts() {
+            let padded = padding.pad(&plaintext);
+            assert_eq!(padding.unpad(&padded), Some(plaintext), "{padding:?}");
+        }
+    }
+}
+
+#[test]
+fn padded_lengths() {
+    for plaintext in plaintexts() {
+        assert_eq!(Padding::None.pad(&plaintext).len(), plaintext.len());
+
+        let signal = Padding::Signal160.pad(&plaintext).len();
+        assert_eq!(signal % 160, 0);
+        assert!(signal > plaintext.len());
+
+        let padme = Padding::Padme.pad(&plaintext).len();
+        assert!(padme > plaintext.len());
+        assert!(padme <= (plaintext.len() + 1) * 112 / 100 + 1);
+    }
+}
+
+#[test]
+fn unpad_rejects_missing_marker() {
+    assert_eq!(Padding::Signal160.unpad(&[0u8; 160]), None);
+    assert_eq!(Padding::Padme.unpad(b"no marker"), None);
+}
+
+#[test]
+fn padded_session_round_trips() {
+    for padding in SCHEMES {
+        let mut alice = User::new("Alice".to_string());
+        let mut bob = User::new("Bob".to_string());
+        alice.set_session_config(SessionConfig {
+            padding,
+            ..SessionConfig::default()
+        });
+
+        for plaintext in plaintexts() {
+            let msg = alice.send_bytes(&bob.public_info(), &plaintext);
+            assert_eq!(
+                bob.receive_bytes(&alice.public_info(), &msg),
+                Some(plaintext)
+            );
+        }
+    }
+}
+
+#[test]
+fn tampered_padding_mode_is_rejected() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    alice.set_session_config(SessionConfig {
+        padding: Padding::Padme,
+        ..SessionConfig::default()
+    });
+
+    let msg = alice.send_message(&bob.public_info(), "hello");
+    let mut tampered = msg.clone();
+    tampered.session_config = tampered.session_config.map(|config| SessionConfig {
+        padding: Padding::None,
+        ..config
+    });
+
+    assert_eq!(
+        bob.try_receive_bytes(&alice.public_info(), &tampered),
+        Err(DecryptError::InvalidMessage)
+    );
+    assert!(bob.session_info(&alice.id).is_none());
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &msg).as_deref(),
+        Some("hello")
+    );
+}
@@ -0,0 +1,39 @@
+//! Content and group control payloads share the pairwise session; receiving one with the
+//! other's API must not consume it.
+
+use signal_protocol_poc::{User, content::Content};
+
+#[test]
+fn content_survives_group_control_receive() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+
+    let (_, msg) = alice.send_content(&bob.public_info(), Content::text("hello"));
+    assert!(
+        bob.receive_group_control(&alice.public_info(), &msg)
+            .is_none()
+    );
+    assert!(bob.session_info(&alice.id).is_none());
+
+    let received = bob.receive_content(&alice.public_info(), &msg).unwrap();
+    assert_eq!(received.content, Content::text("hello"));
+}
+
+#[test]
+fn group_control_survives_content_receive() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+
+    let (_, hello) = alice.send_content(&bob.public_info(), Content::text("hello"));
+    assert!(bob.receive_content(&alice.public_info(), &hello).is_some());
+
+    let msg = alice.send_sender_key(&bob.public_info(), "group");
+    let before = bob.session_info(&alice.id);
+    assert!(bob.receive_content(&alice.public_info(), &msg).is_none());
+    assert_eq!(bob.session_info(&alice.id), before);
+
+    let result = bob
+        .receive_group_control(&alice.public_info(), &msg)
+        .unwrap();
+    assert_eq!(result.group_id, "group");
+}
@@ -0,0 +1,117 @@
+//! A receiver that lost its session asks for a resend with a signed retry request; only
+//! recent requests for cached messages are answered.
+
+use chrono::{TimeDelta, Utc};
+use signal_protocol_poc::{User, keys::encrypted_message::EncryptedMessage};
+
+/// Sets up a session in both directions, then has Bob lose his state (e.g. restored from a
+/// stale backup) so that Alice's next message cannot be decrypted.
+///
+/// # Returns
+/// Alice, Bob, and the message Bob failed to decrypt.
+fn broken_session() -> (User, User, EncryptedMessage) {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    let backup = bob.clone();
+
+    let hello = alice.send_message(&bob.public_info(), "hello");
+    assert!(bob.receive_message(&alice.public_info(), &hello).is_some());
+    let reply = bob.send_message(&alice.public_info(), "hi");
+    assert!(alice.receive_message(&bob.public_info(), &reply).is_some());
+
+    let bob = backup;
+    let lost = alice.send_message(&bob.public_info(), "are you there?");
+    (alice, bob, lost)
+}
+
+#[test]
+fn retry_request_recovers_undecryptable_message() {
+    let (mut alice, mut bob, lost) = broken_session();
+    assert!(bob.try_receive_bytes(&alice.public_info(), &lost).is_err());
+
+    let request = bob.create_retry_request(&alice.public_info(), &lost);
+    let resent = alice
+        .handle_retry_request(&bob.public_info(), &request, Utc::now())
+        .unwrap();
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &resent)
+            .as_deref(),
+        Some("are you there?")
+    );
+
+    let next = alice.send_message(&bob.public_info(), "back on track");
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &next).as_deref(),
+        Some("back on track")
+    );
+    let reply = bob.send_message(&alice.public_info(), "yes");
+    assert_eq!(
+        alice.receive_message(&bob.public_info(), &reply).as_deref(),
+        Some("yes")
+    );
+}
+
+#[test]
+fn retry_request_is_answered_once() {
+    let (mut alice, bob, lost) = broken_session();
+
+    let request = bob.create_retry_request(&alice.public_info(), &lost);
+    let now = Utc::now();
+    assert!(
+        alice
+            .handle_retry_request(&bob.public_info(), &request, now)
+            .is_some()
+    );
+    assert!(
+        alice
+            .handle_retry_request(&bob.public_info(), &request, now)
+            .is_none()
+    );
+}
+
+#[test]
+fn stale_retry_request_is_rejected() {
+    let (mut alice, bob, lost) = broken_session();
+    let info = alice.session_info(&bob.id);
+
+    let request = bob.create_retry_request(&alice.public_info(), &lost);
+    let later = Utc::now() + TimeDelta::days(2);
+    assert!(
+        alice
+            .handle_retry_request(&bob.public_info(), &request, later)
+            .is_none()
+    );
+    assert_eq!(alice.session_info(&bob.id), info);
+
+    assert!(
+        alice
+            .handle_retry_request(&bob.public_info(), &request, Utc::now())
+            .is_some()
+    );
+}
+
+#[test]
+fn future_dated_retry_request_is_rejected() {
+    let (mut alice, bob, lost) = broken_session();
+
+    let request = bob.create_retry_request(&alice.public_info(), &lost);
+    let earlier = Utc::now() - TimeDelta::hours(1);
+    assert!(
+        alice
+            .handle_retry_request(&bob.public_info(), &request, earlier)
+            .is_none()
+    );
+}
+
+#[test]
+fn retry_request_from_another_user_is_rejected() {
+    let (mut alice, bob, lost) = broken_session();
+    let mallory = User::new("Mallory".to_string());
+
+    let request = bob.create_retry_request(&alice.public_info(), &lost);
+    assert!(
+        alice
+            .handle_retry_request(&mallory.public_info(), &request, Utc::now())
+            .is_none()
+    );
+}
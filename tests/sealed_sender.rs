@@ -0,0 +1,140 @@
+//! Sealed-sender messages reveal their sender only to the recipient, and only as the
+//! identity certified by the sender certificate they carry.
+
+use chrono::{Duration, Utc};
+use signal_protocol_poc::{
+    User,
+    sealed_sender::certificate::{CertificateServer, SenderCertificate, TrustRoot},
+};
+
+fn certificate_for(server: &CertificateServer, user: &User) -> SenderCertificate {
+    server.issue_sender_certificate(
+        &user.id,
+        1,
+        user.public_info().ik,
+        Utc::now() + Duration::days(1),
+    )
+}
+
+fn setup() -> (TrustRoot, CertificateServer, User, User) {
+    let root = TrustRoot::new();
+    let server = root.issue_server(1);
+    let alice = User::new("Alice".to_string());
+    let bob = User::new("Bob".to_string());
+    (root, server, alice, bob)
+}
+
+#[test]
+fn sealed_message_round_trips() {
+    let (root, server, mut alice, mut bob) = setup();
+    let certificate = certificate_for(&server, &alice);
+
+    for text in ["hello", "again"] {
+        let sealed = alice.send_sealed_message(&bob.public_info(), text, &certificate);
+        assert_eq!(sealed.receiver, bob.id);
+
+        let (sender, plaintext) = bob
+            .receive_sealed_message(&sealed, &root.public_key())
+            .unwrap();
+        assert_eq!(plaintext, text);
+        assert_eq!(sender.id, alice.id);
+        assert_eq!(sender.device_id, 1);
+        assert_eq!(sender.name, "Alice");
+        assert_eq!(sender.identity_key, alice.public_info().ik);
+    }
+
+    let reply = bob.send_message(&alice.public_info(), "hi");
+    assert_eq!(
+        alice.receive_message(&bob.public_info(), &reply).as_deref(),
+        Some("hi")
+    );
+}
+
+#[test]
+fn message_for_another_receiver_is_rejected() {
+    let (root, server, mut alice, bob) = setup();
+    let mut carol = User::new("Carol".to_string());
+    let certificate = certificate_for(&server, &alice);
+
+    let sealed = alice.send_sealed_message(&bob.public_info(), "hello", &certificate);
+    assert!(
+        carol
+            .receive_sealed_message(&sealed, &root.public_key())
+            .is_none()
+    );
+
+    let mut redirected = sealed.clone();
+    redirected.receiver = carol.id.clone();
+    assert!(
+        carol
+            .receive_sealed_message(&redirected, &root.public_key())
+            .is_none()
+    );
+}
+
+#[test]
+fn tampered_envelope_is_rejected() {
+    let (root, server, mut alice, mut bob) = setup();
+    let certificate = certificate_for(&server, &alice);
+    let sealed = alice.send_sealed_message(&bob.public_info(), "hello", &certificate);
+
+    let mut tampered = sealed.clone();
+    tampered.ciphertext[0] ^= 1;
+    assert!(
+        bob.receive_sealed_message(&tampered, &root.public_key())
+            .is_none()
+    );
+
+    let mut tampered = sealed.clone();
+    tampered.static_box.ciphertext[0] ^= 1;
+    assert!(
+        bob.receive_sealed_message(&tampered, &root.public_key())
+            .is_none()
+    );
+
+    let mut tampered = sealed.clone();
+    tampered.nonce[0] ^= 1;
+    assert!(
+        bob.receive_sealed_message(&tampered, &root.public_key())
+            .is_none()
+    );
+
+    assert!(
+        bob.receive_sealed_message(&sealed, &root.public_key())
+            .is_some()
+    );
+}
+
+#[test]
+fn certificate_of_another_user_is_rejected() {
+    let (root, server, alice, mut bob) = setup();
+    let mut mallory = User::new("Mallory".to_string());
+
+    let stolen = certificate_for(&server, &alice);
+    let sealed = mallory.send_sealed_message(&bob.public_info(), "hello", &stolen);
+    assert!(
+        bob.receive_sealed_message(&sealed, &root.public_key())
+            .is_none()
+    );
+}
+
+#[test]
+fn claimed_name_does_not_change_the_certified_sender() {
+    let (root, server, mut alice, mut bob) = setup();
+    let mut mallory = User::new("Mallory".to_string());
+
+    let hello = alice.send_message(&bob.public_info(), "hello");
+    assert!(bob.receive_message(&alice.public_info(), &hello).is_some());
+    let before = bob.session_info(&alice.id);
+
+    let certificate = certificate_for(&server, &mallory);
+    mallory.name = "Alice".to_string();
+    let sealed = mallory.send_sealed_message(&bob.public_info(), "it's me", &certificate);
+    let (sender, _) = bob
+        .receive_sealed_message(&sealed, &root.public_key())
+        .unwrap();
+
+    assert_eq!(sender.id, mallory.id);
+    assert_ne!(sender.identity_key, alice.public_info().ik);
+    assert_eq!(bob.session_info(&alice.id), before);
+}
@@ -0,0 +1,132 @@
+//! Sender certificates must chain to the trust root, be unexpired, and certify the identity
+//! key that actually sent the sealed message.
+
+use chrono::{DateTime, Duration, Utc};
+use signal_protocol_poc::{
+    User,
+    sealed_sender::certificate::{CertificateServer, SenderCertificate, TrustRoot},
+};
+
+fn issue(server: &CertificateServer, expires: DateTime<Utc>) -> SenderCertificate {
+    server.issue_sender_certificate("alice", 1, [7u8; 32], expires)
+}
+
+#[test]
+fn valid_certificate_is_accepted() {
+    let root = TrustRoot::new();
+    let server = root.issue_server(1);
+    let now = Utc::now();
+
+    assert!(server.certificate.verify(&root.public_key()));
+    assert!(issue(&server, now + Duration::hours(1)).validate(&root.public_key(), now));
+}
+
+#[test]
+fn expired_certificate_is_rejected() {
+    let root = TrustRoot::new();
+    let server = root.issue_server(1);
+    let now = Utc::now();
+    let certificate = issue(&server, now + Duration::hours(1));
+
+    assert!(!certificate.validate(&root.public_key(), now + Duration::hours(1)));
+    assert!(!certificate.validate(&root.public_key(), now + Duration::days(1)));
+}
+
+#[test]
+fn wrong_trust_root_is_rejected() {
+    let root = TrustRoot::new();
+    let other = TrustRoot::new();
+    let now = Utc::now();
+
+    let server = root.issue_server(1);
+    assert!(!server.certificate.verify(&other.public_key()));
+    assert!(!issue(&server, now + Duration::hours(1)).validate(&other.public_key(), now));
+
+    let rogue = other.issue_server(1);
+    assert!(!issue(&rogue, now + Duration::hours(1)).validate(&root.public_key(), now));
+}
+
+#[test]
+fn tampered_server_certificate_is_rejected() {
+    let root = TrustRoot::new();
+    let server = root.issue_server(1);
+    let now = Utc::now();
+    let certificate = issue(&server, now + Duration::hours(1));
+
+    let mut tampered = certificate.clone();
+    tampered.signer.signature[0] ^= 1;
+    assert!(!tampered.signer.verify(&root.public_key()));
+    assert!(!tampered.validate(&root.public_key(), now));
+
+    let mut tampered = certificate.clone();
+    tampered.signer.key_id = 2;
+    assert!(!tampered.validate(&root.public_key(), now));
+
+    let mut tampered = certificate;
+    tampered.signer.key = root.issue_server(1).certificate.key;
+    assert!(!tampered.validate(&root.public_key(), now));
+}
+
+#[test]
+fn tampered_sender_certificate_is_rejected() {
+    let root = TrustRoot::new();
+    let server = root.issue_server(1);
+    let now = Utc::now();
+    let certificate = issue(&server, now + Duration::hours(1));
+
+    let mut tampered = certificate.clone();
+    tampered.signature[0] ^= 1;
+    assert!(!tampered.validate(&root.public_key(), now));
+
+    let mut tampered = certificate.clone();
+    tampered.sender_id = "mallory".to_string();
+    assert!(!tampered.validate(&root.public_key(), now));
+
+    let mut tampered = certificate.clone();
+    tampered.identity_key = [8u8; 32];
+    assert!(!tampered.validate(&root.public_key(), now));
+
+    let mut tampered = certificate;
+    tampered.expires += Duration::days(365);
+    assert!(!tampered.validate(&root.public_key(), now));
+}
+
+#[test]
+fn certificate_for_another_identity_key_is_rejected() {
+    let root = TrustRoot::new();
+    let server = root.issue_server(1);
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    let mallory = User::new("Mallory".to_string());
+    let expires = Utc::now() + Duration::hours(1);
+
+    let certificate =
+        server.issue_sender_certificate(&alice.id, 1, mallory.public_info().ik, expires);
+    assert!(certificate.validate(&root.public_key(), Utc::now()));
+
+    let sealed = alice.send_sealed_message(&bob.public_info(), "hello", &certificate);
+    assert!(
+        bob.receive_sealed_message(&sealed, &root.public_key())
+            .is_none()
+    );
+}
+
+#[test]
+fn expired_certificate_is_rejected_by_the_recipient() {
+    let root = TrustRoot::new();
+    let server = root.issue_server(1);
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+
+    let certificate = server.issue_sender_certificate(
+        &alice.id,
+        1,
+        alice.public_info().ik,
+        Utc::now() - Duration::seconds(1),
+    );
+    let sealed = alice.send_sealed_message(&bob.public_info(), "hello", &certificate);
+    assert!(
+        bob.receive_sealed_message(&sealed, &root.public_key())
+            .is_none()
+    );
+}
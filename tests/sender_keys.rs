@@ -0,0 +1,69 @@
+//! Group messages are only sent under a sender key that was distributed to the group, and
+//! receivers bound the number of keys they keep for late group messages.
+
+use signal_protocol_poc::User;
+
+/// Creates a group of Alice and Bob and delivers Alice's sender key to Bob.
+///
+/// # Returns
+/// The new group ID.
+fn setup(alice: &mut User, bob: &mut User) -> String {
+    let (group_id, outgoing) = alice.create_group("friends", &[bob.public_info()]);
+    let alice_info = alice.public_info();
+    for (_, msg) in outgoing {
+        assert!(bob.receive_group_control(&alice_info, &msg).is_some());
+    }
+    group_id
+}
+
+#[test]
+fn sending_to_unknown_group_is_refused() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    setup(&mut alice, &mut bob);
+
+    assert!(alice.send_group_message("unknown-group", "hi").is_none());
+    assert!(bob.send_group_bytes("unknown-group", b"hi").is_none());
+}
+
+#[test]
+fn oldest_skipped_sender_keys_are_evicted() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    let group_id = setup(&mut alice, &mut bob);
+    let alice_info = alice.public_info();
+
+    let msgs: Vec<_> = (0..4002)
+        .map(|i| {
+            alice
+                .send_group_message(&group_id, &format!("m{i}"))
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(
+        bob.receive_group_message(&alice_info, &msgs[2000])
+            .as_deref(),
+        Some("m2000")
+    );
+    assert_eq!(
+        bob.receive_group_message(&alice_info, &msgs[4001])
+            .as_deref(),
+        Some("m4001")
+    );
+
+    assert!(bob.receive_group_message(&alice_info, &msgs[0]).is_none());
+    assert!(
+        bob.receive_group_message(&alice_info, &msgs[1999])
+            .is_none()
+    );
+    assert_eq!(
+        bob.receive_group_message(&alice_info, &msgs[2001])
+            .as_deref(),
+        Some("m2001")
+    );
+    assert_eq!(
+        bob.receive_group_message(&alice_info, &msgs[4000])
+            .as_deref(),
+        Some("m4000")
+    );
+}
@@ -0,0 +1,63 @@
+//! `SessionInfo` must report the ratchet progress of a session without exposing keys.
+
+use chrono::Utc;
+use signal_protocol_poc::{User, crypto_utils::cipher_suite::CipherSuiteId};
+
+#[test]
+fn session_info_tracks_ratchet_progress() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    assert_eq!(alice.session_info(&bob.id), None);
+
+    let msgs: Vec<_> = (0..3)
+        .map(|i| alice.send_message(&bob.public_info(), &format!("a{i}")))
+        .collect();
+    let info = alice.session_info(&bob.id).unwrap();
+    assert_eq!(info.peer_identity_key, bob.public_info().ik);
+    assert_eq!(info.ratchet_steps, 0);
+    assert_eq!(info.sending_index, 3);
+    assert_eq!(info.receiving_index, 0);
+    assert_eq!(info.skipped_keys, 0);
+    assert!(info.x3dh_pending);
+    assert_eq!(info.cipher_suite, CipherSuiteId::ChaCha20Poly1305Sha256);
+    assert!(info.created_at <= Utc::now());
+
+    assert!(
+        bob.receive_message(&alice.public_info(), &msgs[0])
+            .is_some()
+    );
+    assert!(
+        bob.receive_message(&alice.public_info(), &msgs[2])
+            .is_some()
+    );
+    let info = bob.session_info(&alice.id).unwrap();
+    assert_eq!(info.peer_identity_key, alice.public_info().ik);
+    assert_eq!(info.ratchet_steps, 1);
+    assert_eq!(info.sending_index, 0);
+    assert_eq!(info.receiving_index, 3);
+    assert_eq!(info.skipped_keys, 1);
+    assert!(!info.x3dh_pending);
+
+    let reply = bob.send_message(&alice.public_info(), "b0");
+    assert_eq!(bob.session_info(&alice.id).unwrap().sending_index, 1);
+    assert!(alice.session_info(&bob.id).unwrap().x3dh_pending);
+    assert!(alice.receive_message(&bob.public_info(), &reply).is_some());
+    let info = alice.session_info(&bob.id).unwrap();
+    assert!(!info.x3dh_pending);
+    assert_eq!(info.ratchet_steps, 1);
+    assert_eq!(info.receiving_index, 1);
+
+    let next = alice.send_message(&bob.public_info(), "a3");
+    assert_eq!(alice.session_info(&bob.id).unwrap().sending_index, 1);
+    assert!(bob.receive_message(&alice.public_info(), &next).is_some());
+    let info = bob.session_info(&alice.id).unwrap();
+    assert_eq!(info.ratchet_steps, 2);
+    assert_eq!(info.receiving_index, 1);
+    assert_eq!(info.skipped_keys, 1);
+
+    assert!(
+        bob.receive_message(&alice.public_info(), &msgs[1])
+            .is_some()
+    );
+    assert_eq!(bob.session_info(&alice.id).unwrap().skipped_keys, 0);
+}
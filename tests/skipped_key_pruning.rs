@@ -0,0 +1,121 @@
+//! Skipped message keys are purged once they exceed the retention policy, while keys
+//! within the policy keep decrypting late messages.
+
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use signal_protocol_poc::{User, double_ratchet::config::SkippedKeyPolicy};
+
+/// Has Bob reply and Alice answer, so that Bob performs one receiving DH ratchet step.
+fn ratchet_step(alice: &mut User, bob: &mut User) {
+    let reply = bob.send_message(&alice.public_info(), "pong");
+    assert!(alice.receive_message(&bob.public_info(), &reply).is_some());
+    let msg = alice.send_message(&bob.public_info(), "ping");
+    assert!(bob.receive_message(&alice.public_info(), &msg).is_some());
+}
+
+#[test]
+fn keys_older_than_max_age_are_pruned() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    bob.set_skipped_key_policy(SkippedKeyPolicy {
+        max_age: Some(Duration::from_secs(60 * 60)),
+        max_ratchet_steps: None,
+    });
+
+    let msgs: Vec<_> = (0..4)
+        .map(|i| alice.send_message(&bob.public_info(), &format!("a{i}")))
+        .collect();
+    assert!(
+        bob.receive_message(&alice.public_info(), &msgs[0])
+            .is_some()
+    );
+    assert!(
+        bob.receive_message(&alice.public_info(), &msgs[3])
+            .is_some()
+    );
+    assert_eq!(bob.session_info(&alice.id).unwrap().skipped_keys, 2);
+
+    assert_eq!(
+        bob.prune_skipped_keys(Utc::now() + TimeDelta::minutes(30)),
+        0
+    );
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &msgs[1])
+            .as_deref(),
+        Some("a1")
+    );
+
+    assert_eq!(bob.prune_skipped_keys(Utc::now() + TimeDelta::hours(2)), 1);
+    assert_eq!(bob.session_info(&alice.id).unwrap().skipped_keys, 0);
+    assert!(
+        bob.try_receive_bytes(&alice.public_info(), &msgs[2])
+            .is_err()
+    );
+}
+
+#[test]
+fn keys_older_than_max_ratchet_steps_are_pruned() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+    bob.set_skipped_key_policy(SkippedKeyPolicy {
+        max_age: None,
+        max_ratchet_steps: Some(1),
+    });
+
+    let old: Vec<_> = (0..3)
+        .map(|i| alice.send_message(&bob.public_info(), &format!("a{i}")))
+        .collect();
+    assert!(bob.receive_message(&alice.public_info(), &old[0]).is_some());
+    assert!(bob.receive_message(&alice.public_info(), &old[2]).is_some());
+
+    ratchet_step(&mut alice, &mut bob);
+    assert_eq!(bob.prune_skipped_keys(Utc::now()), 0);
+
+    let reply = bob.send_message(&alice.public_info(), "pong");
+    assert!(alice.receive_message(&bob.public_info(), &reply).is_some());
+    let recent: Vec<_> = (0..2)
+        .map(|i| alice.send_message(&bob.public_info(), &format!("c{i}")))
+        .collect();
+    assert!(
+        bob.receive_message(&alice.public_info(), &recent[1])
+            .is_some()
+    );
+    assert_eq!(bob.session_info(&alice.id).unwrap().skipped_keys, 2);
+
+    assert_eq!(bob.prune_skipped_keys(Utc::now()), 1);
+    assert!(
+        bob.try_receive_bytes(&alice.public_info(), &old[1])
+            .is_err()
+    );
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &recent[0])
+            .as_deref(),
+        Some("c0")
+    );
+}
+
+#[test]
+fn default_policy_keeps_recent_keys() {
+    let mut alice = User::new("Alice".to_string());
+    let mut bob = User::new("Bob".to_string());
+
+    let msgs: Vec<_> = (0..3)
+        .map(|i| alice.send_message(&bob.public_info(), &format!("a{i}")))
+        .collect();
+    assert!(
+        bob.receive_message(&alice.public_info(), &msgs[0])
+            .is_some()
+    );
+    assert!(
+        bob.receive_message(&alice.public_info(), &msgs[2])
+            .is_some()
+    );
+
+    assert_eq!(bob.prune_skipped_keys(Utc::now() + TimeDelta::days(29)), 0);
+    assert_eq!(
+        bob.receive_message(&alice.public_info(), &msgs[1])
+            .as_deref(),
+        Some("a1")
+    );
+}
@@ -0,0 +1,186 @@
+//! TreeKEM group membership changes: adds, removals, updates and commits must move every
+//! member to the same epoch, and stale or foreign handshakes must be rejected.
+
+use ed25519_dalek::{Signer, SigningKey};
+use signal_protocol_poc::treekem::{
+    group::{CommitOutcome, TreeKemGroup},
+    messages::{KeyPackageBundle, Proposal},
+};
+
+/// Creates a group owned by the first id and adds the others in one commit.
+fn setup(ids: &[&str]) -> Vec<TreeKemGroup> {
+    let bundles: Vec<KeyPackageBundle> = ids.iter().map(|id| KeyPackageBundle::new(id)).collect();
+    let mut creator = TreeKemGroup::create("group", &bundles[0]);
+    let key_packages: Vec<_> = bundles[1..].iter().map(|b| b.key_package.clone()).collect();
+    let (_, welcomes) = creator.add_members(&key_packages).unwrap();
+
+    let mut groups = vec![creator];
+    for (bundle, welcome) in bundles[1..].iter().zip(&welcomes) {
+        assert_eq!(welcome.member_id, bundle.key_package.leaf.member_id);
+        groups.push(TreeKemGroup::join(welcome, bundle).unwrap());
+    }
+    groups
+}
+
+fn assert_all_can_talk(groups: &mut [TreeKemGroup]) {
+    for sender in 0..groups.len() {
+        let msg = groups[sender].encrypt(b"hello");
+        for (receiver, group) in groups.iter_mut().enumerate() {
+            if receiver != sender {
+                assert_eq!(group.decrypt(&msg).as_deref(), Some(&b"hello"[..]));
+            }
+        }
+    }
+}
+
+#[test]
+fn added_members_share_the_epoch() {
+    let mut groups = setup(&["Alice", "Bob", "Carol"]);
+
+    for group in &groups {
+        assert_eq!(group.epoch(), 1);
+        assert_eq!(group.members(), ["Alice", "Bob", "Carol"]);
+    }
+    assert_all_can_talk(&mut groups);
+}
+
+#[test]
+fn later_add_is_processed_by_existing_members() {
+    let mut groups = setup(&["Alice", "Bob"]);
+    let dave = KeyPackageBundle::new("Dave");
+
+    let (commit, welcomes) = groups[1]
+        .add_members(std::slice::from_ref(&dave.key_package))
+        .unwrap();
+    assert_eq!(
+        groups[0].process_commit(&commit),
+        Some(CommitOutcome::Applied)
+    );
+    groups.push(TreeKemGroup::join(&welcomes[0], &dave).unwrap());
+
+    for group in &groups {
+        assert_eq!(group.epoch(), 2);
+        assert_eq!(group.members(), ["Alice", "Bob", "Dave"]);
+    }
+    assert_all_can_talk(&mut groups);
+}
+
+#[test]
+fn removed_member_is_locked_out() {
+    let mut groups = setup(&["Alice", "Bob", "Carol"]);
+
+    let commit = groups[0].remove_members(&["Carol"]).unwrap();
+    assert_eq!(
+        groups[1].process_commit(&commit),
+        Some(CommitOutcome::Applied)
+    );
+    assert_eq!(
+        groups[2].process_commit(&commit),
+        Some(CommitOutcome::Removed)
+    );
+
+    let msg = groups[0].encrypt(b"after removal");
+    assert_eq!(
+        groups[1].decrypt(&msg).as_deref(),
+        Some(&b"after removal"[..])
+    );
+    assert_eq!(groups[2].decrypt(&msg), None);
+    assert_eq!(groups[0].members(), ["Alice", "Bob"]);
+    assert_eq!(groups[1].members(), ["Alice", "Bob"]);
+}
+
+#[test]
+fn update_advances_the_epoch() {
+    let mut groups = setup(&["Alice", "Bob", "Carol"]);
+
+    let commit = groups[1].update();
+    assert_eq!(
+        groups[0].process_commit(&commit),
+        Some(CommitOutcome::Applied)
+    );
+    assert_eq!(
+        groups[2].process_commit(&commit),
+        Some(CommitOutcome::Applied)
+    );
+
+    for group in &groups {
+        assert_eq!(group.epoch(), 2);
+    }
+    assert_all_can_talk(&mut groups);
+}
+
+#[test]
+fn commit_for_another_epoch_is_rejected() {
+    let mut groups = setup(&["Alice", "Bob", "Carol"]);
+
+    let first = groups[0].update();
+    assert_eq!(
+        groups[1].process_commit(&first),
+        Some(CommitOutcome::Applied)
+    );
+    assert_eq!(groups[1].process_commit(&first), None);
+
+    let second = groups[0].update();
+    assert_eq!(groups[2].process_commit(&second), None);
+    assert_eq!(groups[2].epoch(), 1);
+
+    assert_eq!(
+        groups[2].process_commit(&first),
+        Some(CommitOutcome::Applied)
+    );
+    assert_eq!(
+        groups[2].process_commit(&second),
+        Some(CommitOutcome::Applied)
+    );
+    assert_eq!(
+        groups[1].process_commit(&second),
+        Some(CommitOutcome::Applied)
+    );
+    assert_all_can_talk(&mut groups);
+}
+
+#[test]
+fn application_message_from_old_epoch_is_rejected() {
+    let mut groups = setup(&["Alice", "Bob"]);
+
+    let stale = groups[0].encrypt(b"old epoch");
+    let commit = groups[0].update();
+    assert_eq!(
+        groups[1].process_commit(&commit),
+        Some(CommitOutcome::Applied)
+    );
+
+    assert_eq!(groups[1].decrypt(&stale), None);
+}
+
+#[test]
+fn tampered_commit_is_rejected() {
+    let mut groups = setup(&["Alice", "Bob", "Carol"]);
+
+    let mut commit = groups[0].remove_members(&["Carol"]).unwrap();
+    commit.content.proposals = vec![Proposal::Remove("Bob".to_string())];
+
+    assert_eq!(groups[1].process_commit(&commit), None);
+    assert_eq!(groups[1].epoch(), 1);
+    assert_eq!(groups[2].process_commit(&commit), None);
+}
+
+#[test]
+fn commit_removing_its_committer_is_rejected() {
+    let mut groups = setup(&["Alice", "Bob", "Carol"]);
+
+    // Re-sign a commit that removes Alice with Alice's own leaf key, as a client that
+    // skips the local check in `commit` would.
+    let state = serde_json::to_value(&groups[0]).unwrap();
+    let seed: [u8; 32] = serde_json::from_value(state["signature_private"].clone()).unwrap();
+    let mut commit = groups[0].update();
+    commit.content.proposals = vec![Proposal::Remove("Alice".to_string())];
+    commit.signature = SigningKey::from_bytes(&seed)
+        .sign(&serde_json::to_vec(&commit.content).unwrap())
+        .to_bytes()
+        .to_vec();
+
+    assert_eq!(groups[1].process_commit(&commit), None);
+    assert_eq!(groups[2].process_commit(&commit), None);
+    assert_eq!(groups[1].members(), ["Alice", "Bob", "Carol"]);
+}